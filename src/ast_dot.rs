@@ -0,0 +1,359 @@
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Write,
+    rc::Rc,
+};
+
+use crate::{
+    expr::{Expr, ExpressionVisitor, ScopeDepth, VariableData},
+    literal::Literal,
+    stmt::{FunctionData, StatementVisitor, Stmt},
+    token::Token,
+};
+
+/// Writes the syntax tree as a Graphviz `dot` graph. Every node gets its own
+/// id, so identical subtrees are drawn separately. Expressions are boxes and
+/// statements are ellipses.
+pub struct DotPrinter {
+    next_id: Cell<usize>,
+    output: RefCell<String>,
+}
+
+impl DotPrinter {
+    pub fn new() -> Self {
+        Self {
+            next_id: Cell::new(0),
+            output: RefCell::new(String::new()),
+        }
+    }
+
+    pub fn print(&self, expr: &Expr) -> String {
+        self.graph(|| {
+            expr.accept(self);
+        })
+    }
+
+    /// Prints a whole program, hanging every statement off a `program` root.
+    pub fn print_program(&self, statements: &[Stmt]) -> String {
+        self.graph(|| {
+            let root = self.node("program", "ellipse");
+            self.stmts(root, statements, "");
+        })
+    }
+
+    fn graph(&self, body: impl FnOnce()) -> String {
+        self.next_id.set(0);
+        *self.output.borrow_mut() =
+            String::from("digraph ast {\n  node [fontname=\"monospace\"];\n");
+        body();
+        let mut output = self.output.take();
+        output += "}\n";
+        output
+    }
+
+    fn node(&self, label: &str, shape: &str) -> usize {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        let _ = writeln!(
+            self.output.borrow_mut(),
+            "  n{id} [label={}, shape={shape}];",
+            quote(label)
+        );
+        id
+    }
+
+    fn expr_node(&self, label: &str) -> usize {
+        self.node(label, "box")
+    }
+
+    fn stmt_node(&self, label: &str) -> usize {
+        self.node(label, "ellipse")
+    }
+
+    fn edge(&self, from: usize, to: usize, label: &str) {
+        let mut output = self.output.borrow_mut();
+        if label.is_empty() {
+            let _ = writeln!(output, "  n{from} -> n{to};");
+        } else {
+            let _ = writeln!(output, "  n{from} -> n{to} [label={}];", quote(label));
+        }
+    }
+
+    fn expr(&self, parent: usize, expr: &Expr, label: &str) {
+        let id = expr.accept(self);
+        self.edge(parent, id, label);
+    }
+
+    fn stmt(&self, parent: usize, stmt: &Stmt, label: &str) {
+        let id = stmt.accept(self);
+        self.edge(parent, id, label);
+    }
+
+    fn stmts(&self, parent: usize, statements: &[Stmt], label: &str) {
+        for statement in statements {
+            self.stmt(parent, statement, label);
+        }
+    }
+
+    fn function(&self, keyword: &str, declaration: &FunctionData) -> usize {
+        let params: Vec<&str> = declaration
+            .params
+            .iter()
+            .map(|param| param.lexeme.as_str())
+            .collect();
+        let id = self.stmt_node(&format!(
+            "{keyword} {}({})",
+            declaration.name.lexeme,
+            params.join(", ")
+        ));
+        self.stmts(id, &declaration.body, "");
+        id
+    }
+}
+
+/// Quotes `text` as a `dot` string.
+fn quote(text: &str) -> String {
+    let mut output = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => output += "\\\"",
+            '\\' => output += "\\\\",
+            '\n' => output += "\\n",
+            c => output.push(c),
+        }
+    }
+    output += "\"";
+    output
+}
+
+impl ExpressionVisitor<usize> for DotPrinter {
+    fn visit_binary(&self, left: &Expr, operand: &Token, right: &Expr) -> usize {
+        let id = self.expr_node(&operand.lexeme);
+        self.expr(id, left, "");
+        self.expr(id, right, "");
+        id
+    }
+
+    fn visit_unary(&self, operand: &Token, expr: &Expr) -> usize {
+        let id = self.expr_node(&operand.lexeme);
+        self.expr(id, expr, "");
+        id
+    }
+
+    fn visit_literal(&self, literal: &Literal) -> usize {
+        match literal {
+            Literal::String(string) => self.expr_node(&format!("{string:?}")),
+            literal => self.expr_node(&literal.to_string()),
+        }
+    }
+
+    fn visit_grouping(&self, expr: &Expr) -> usize {
+        let id = self.expr_node("group");
+        self.expr(id, expr, "");
+        id
+    }
+
+    fn visit_variable(&self, name: &Token, _depth: &ScopeDepth) -> usize {
+        self.expr_node(&name.lexeme)
+    }
+
+    fn visit_assign(&self, name: &Token, value: &Expr, _depth: &ScopeDepth) -> usize {
+        let id = self.expr_node(&format!("{} =", name.lexeme));
+        self.expr(id, value, "");
+        id
+    }
+
+    fn visit_logical(&self, left: &Expr, operator: &Token, right: &Expr) -> usize {
+        let id = self.expr_node(&operator.lexeme);
+        self.expr(id, left, "");
+        self.expr(id, right, "");
+        id
+    }
+
+    fn visit_conditional(&self, condition: &Expr, then_branch: &Expr, else_branch: &Expr) -> usize {
+        let id = self.expr_node("?:");
+        self.expr(id, condition, "condition");
+        self.expr(id, then_branch, "then");
+        self.expr(id, else_branch, "else");
+        id
+    }
+
+    fn visit_call(&self, callee: &Expr, _paren: &Token, arguments: &[Expr]) -> usize {
+        let id = self.expr_node("call");
+        self.expr(id, callee, "callee");
+        for (index, argument) in arguments.iter().enumerate() {
+            self.expr(id, argument, &index.to_string());
+        }
+        id
+    }
+
+    fn visit_get(&self, object: &Expr, name: &Token) -> usize {
+        let id = self.expr_node(&format!(".{}", name.lexeme));
+        self.expr(id, object, "");
+        id
+    }
+
+    fn visit_set(&self, object: &Expr, name: &Token, value: &Expr) -> usize {
+        let id = self.expr_node(&format!(".{} =", name.lexeme));
+        self.expr(id, object, "object");
+        self.expr(id, value, "value");
+        id
+    }
+
+    fn visit_this(&self, keyword: &Token, _depth: &ScopeDepth) -> usize {
+        self.expr_node(&keyword.lexeme)
+    }
+
+    fn visit_super(&self, keyword: &Token, method: &Token, _depth: &ScopeDepth) -> usize {
+        self.expr_node(&format!("{}.{}", keyword.lexeme, method.lexeme))
+    }
+}
+
+impl StatementVisitor<usize> for DotPrinter {
+    fn visit_expression(&self, expr: &Expr) -> usize {
+        let id = self.stmt_node("expression");
+        self.expr(id, expr, "");
+        id
+    }
+
+    fn visit_print(&self, expr: &Expr) -> usize {
+        let id = self.stmt_node("print");
+        self.expr(id, expr, "");
+        id
+    }
+
+    fn visit_var(&self, name: &Token, initializer: Option<&Expr>) -> usize {
+        let id = self.stmt_node(&format!("var {}", name.lexeme));
+        if let Some(initializer) = initializer {
+            self.expr(id, initializer, "");
+        }
+        id
+    }
+
+    fn visit_block(&self, statements: &[Stmt]) -> usize {
+        let id = self.stmt_node("block");
+        self.stmts(id, statements, "");
+        id
+    }
+
+    fn visit_if(&self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>) -> usize {
+        let id = self.stmt_node("if");
+        self.expr(id, condition, "condition");
+        self.stmt(id, then_branch, "then");
+        if let Some(else_branch) = else_branch {
+            self.stmt(id, else_branch, "else");
+        }
+        id
+    }
+
+    fn visit_while(&self, condition: &Expr, body: &Stmt, increment: Option<&Expr>) -> usize {
+        let id = self.stmt_node("while");
+        self.expr(id, condition, "condition");
+        self.stmt(id, body, "body");
+        if let Some(increment) = increment {
+            self.expr(id, increment, "increment");
+        }
+        id
+    }
+
+    fn visit_break(&self, _keyword: &Token) -> usize {
+        self.stmt_node("break")
+    }
+
+    fn visit_continue(&self, _keyword: &Token) -> usize {
+        self.stmt_node("continue")
+    }
+
+    fn visit_function(&self, declaration: &Rc<FunctionData>) -> usize {
+        self.function("fun", declaration)
+    }
+
+    fn visit_return(&self, _keyword: &Token, value: Option<&Expr>) -> usize {
+        let id = self.stmt_node("return");
+        if let Some(value) = value {
+            self.expr(id, value, "");
+        }
+        id
+    }
+
+    fn visit_class(
+        &self,
+        name: &Token,
+        superclass: Option<&VariableData>,
+        methods: &[Rc<FunctionData>],
+    ) -> usize {
+        let label = match superclass {
+            Some(superclass) => format!("class {} < {}", name.lexeme, superclass.name.lexeme),
+            None => format!("class {}", name.lexeme),
+        };
+        let id = self.stmt_node(&label);
+        for method in methods {
+            let method = self.function("method", method);
+            self.edge(id, method, "");
+        }
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner};
+
+    fn print(source: &str) -> String {
+        let tokens = Scanner::new(source.as_bytes()).scan_tokens().unwrap();
+        let statements = Parser::new(&tokens).parse().unwrap();
+        DotPrinter::new().print_program(&statements)
+    }
+
+    /// The `(id, label)` of every node in `graph`, in order.
+    fn nodes(graph: &str) -> Vec<(&str, &str)> {
+        graph
+            .lines()
+            .filter_map(|line| {
+                let (id, rest) = line.trim().split_once(" [label=")?;
+                let (label, _) = rest.rsplit_once(", shape=")?;
+                Some((id, label))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn gives_every_node_its_own_id() {
+        let graph = print("print 1 + 1; print 1 + 1;");
+        let nodes = nodes(&graph);
+        let ids: HashSet<_> = nodes.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids.len(), nodes.len());
+        assert_eq!(nodes.iter().filter(|(_, label)| *label == "\"1\"").count(), 4);
+
+        for line in graph.lines().filter(|line| line.contains("->")) {
+            let (from, rest) = line.trim().split_once(" -> ").unwrap();
+            let to = rest.trim_end_matches(';').split(' ').next().unwrap();
+            assert!(ids.contains(from) && ids.contains(to), "dangling edge: {line}");
+        }
+    }
+
+    #[test]
+    fn labels_operators_and_literals() {
+        let graph = print("print -2 * (3 - \"s\") == nil;");
+        let labels: Vec<_> = nodes(&graph).into_iter().map(|(_, label)| label).collect();
+        assert_eq!(
+            labels,
+            [
+                "\"program\"",
+                "\"print\"",
+                "\"==\"",
+                "\"*\"",
+                "\"-\"",
+                "\"2\"",
+                "\"group\"",
+                "\"-\"",
+                "\"3\"",
+                "\"\\\"s\\\"\"",
+                "\"nil\"",
+            ]
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use std::rc::Rc;
+
+use crate::{
+    expr::{Expr, ExpressionVisitor, ScopeDepth, VariableData},
+    json,
+    literal::Literal,
+    stmt::{FunctionData, StatementVisitor, Stmt},
+    token::{Span, Token},
+};
+
+/// Writes the syntax tree as JSON. Every node is an object with a `kind`
+/// naming its variant; expressions also carry the `span` they were parsed
+/// from.
+pub struct JsonPrinter;
+
+impl JsonPrinter {
+    pub fn print(&self, expr: &Expr) -> String {
+        self.expr(expr)
+    }
+
+    /// Prints a program as a JSON array with one statement per line.
+    pub fn print_program(&self, statements: &[Stmt]) -> String {
+        let statements: Vec<String> = statements
+            .iter()
+            .map(|statement| format!("  {}", self.stmt(statement)))
+            .collect();
+        if statements.is_empty() {
+            return "[]".to_string();
+        }
+        format!("[\n{}\n]", statements.join(",\n"))
+    }
+
+    fn expr(&self, expr: &Expr) -> String {
+        format!("{{{},\"span\":{}}}", expr.accept(self), span(expr.span()))
+    }
+
+    fn stmt(&self, stmt: &Stmt) -> String {
+        format!("{{{}}}", stmt.accept(self))
+    }
+
+    fn optional_expr(&self, expr: Option<&Expr>) -> String {
+        expr.map_or_else(|| "null".to_string(), |expr| self.expr(expr))
+    }
+
+    fn stmts(&self, statements: &[Stmt]) -> String {
+        let statements: Vec<String> = statements.iter().map(|s| self.stmt(s)).collect();
+        format!("[{}]", statements.join(","))
+    }
+
+    fn function(&self, declaration: &FunctionData) -> String {
+        let params: Vec<String> = declaration
+            .params
+            .iter()
+            .map(|param| json::string(&param.lexeme))
+            .collect();
+        format!(
+            "{},\"name\":{},\"params\":[{}],\"body\":{}",
+            kind("Function"),
+            json::string(&declaration.name.lexeme),
+            params.join(","),
+            self.stmts(&declaration.body)
+        )
+    }
+}
+
+fn kind(name: &str) -> String {
+    format!("\"kind\":{}", json::string(name))
+}
+
+fn span(span: Span) -> String {
+    format!(
+        "{{\"start\":{},\"end\":{},\"line\":{},\"column\":{}}}",
+        span.start, span.end, span.line, span.column
+    )
+}
+
+impl ExpressionVisitor<String> for JsonPrinter {
+    fn visit_binary(&self, left: &Expr, operand: &Token, right: &Expr) -> String {
+        format!(
+            "{},\"operator\":{},\"left\":{},\"right\":{}",
+            kind("Binary"),
+            json::string(&operand.lexeme),
+            self.expr(left),
+            self.expr(right)
+        )
+    }
+
+    fn visit_unary(&self, operand: &Token, expr: &Expr) -> String {
+        format!(
+            "{},\"operator\":{},\"right\":{}",
+            kind("Unary"),
+            json::string(&operand.lexeme),
+            self.expr(expr)
+        )
+    }
+
+    fn visit_literal(&self, literal: &Literal) -> String {
+        let value = match literal {
+            Literal::Number(number) => json::number(*number),
+            Literal::String(string) => json::string(string),
+            Literal::Bool(bool) => bool.to_string(),
+            Literal::Nil => "null".to_string(),
+        };
+        format!("{},\"value\":{value}", kind("Literal"))
+    }
+
+    fn visit_grouping(&self, expr: &Expr) -> String {
+        format!("{},\"expression\":{}", kind("Grouping"), self.expr(expr))
+    }
+
+    fn visit_variable(&self, name: &Token, _depth: &ScopeDepth) -> String {
+        format!(
+            "{},\"name\":{}",
+            kind("Variable"),
+            json::string(&name.lexeme)
+        )
+    }
+
+    fn visit_assign(&self, name: &Token, value: &Expr, _depth: &ScopeDepth) -> String {
+        format!(
+            "{},\"name\":{},\"value\":{}",
+            kind("Assign"),
+            json::string(&name.lexeme),
+            self.expr(value)
+        )
+    }
+
+    fn visit_logical(&self, left: &Expr, operator: &Token, right: &Expr) -> String {
+        format!(
+            "{},\"operator\":{},\"left\":{},\"right\":{}",
+            kind("Logical"),
+            json::string(&operator.lexeme),
+            self.expr(left),
+            self.expr(right)
+        )
+    }
+
+    fn visit_conditional(
+        &self,
+        condition: &Expr,
+        then_branch: &Expr,
+        else_branch: &Expr,
+    ) -> String {
+        format!(
+            "{},\"condition\":{},\"then_branch\":{},\"else_branch\":{}",
+            kind("Conditional"),
+            self.expr(condition),
+            self.expr(then_branch),
+            self.expr(else_branch)
+        )
+    }
+
+    fn visit_call(&self, callee: &Expr, _paren: &Token, arguments: &[Expr]) -> String {
+        let arguments: Vec<String> = arguments.iter().map(|a| self.expr(a)).collect();
+        format!(
+            "{},\"callee\":{},\"arguments\":[{}]",
+            kind("Call"),
+            self.expr(callee),
+            arguments.join(",")
+        )
+    }
+
+    fn visit_get(&self, object: &Expr, name: &Token) -> String {
+        format!(
+            "{},\"object\":{},\"name\":{}",
+            kind("Get"),
+            self.expr(object),
+            json::string(&name.lexeme)
+        )
+    }
+
+    fn visit_set(&self, object: &Expr, name: &Token, value: &Expr) -> String {
+        format!(
+            "{},\"object\":{},\"name\":{},\"value\":{}",
+            kind("Set"),
+            self.expr(object),
+            json::string(&name.lexeme),
+            self.expr(value)
+        )
+    }
+
+    fn visit_this(&self, _keyword: &Token, _depth: &ScopeDepth) -> String {
+        kind("This")
+    }
+
+    fn visit_super(&self, _keyword: &Token, method: &Token, _depth: &ScopeDepth) -> String {
+        format!(
+            "{},\"method\":{}",
+            kind("Super"),
+            json::string(&method.lexeme)
+        )
+    }
+}
+
+impl StatementVisitor<String> for JsonPrinter {
+    fn visit_expression(&self, expr: &Expr) -> String {
+        format!("{},\"expression\":{}", kind("Expression"), self.expr(expr))
+    }
+
+    fn visit_print(&self, expr: &Expr) -> String {
+        format!("{},\"expression\":{}", kind("Print"), self.expr(expr))
+    }
+
+    fn visit_var(&self, name: &Token, initializer: Option<&Expr>) -> String {
+        format!(
+            "{},\"name\":{},\"initializer\":{}",
+            kind("Var"),
+            json::string(&name.lexeme),
+            self.optional_expr(initializer)
+        )
+    }
+
+    fn visit_block(&self, statements: &[Stmt]) -> String {
+        format!(
+            "{},\"statements\":{}",
+            kind("Block"),
+            self.stmts(statements)
+        )
+    }
+
+    fn visit_if(&self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>) -> String {
+        format!(
+            "{},\"condition\":{},\"then_branch\":{},\"else_branch\":{}",
+            kind("If"),
+            self.expr(condition),
+            self.stmt(then_branch),
+            else_branch.map_or_else(|| "null".to_string(), |s| self.stmt(s))
+        )
+    }
+
+    fn visit_while(&self, condition: &Expr, body: &Stmt, increment: Option<&Expr>) -> String {
+        format!(
+            "{},\"condition\":{},\"body\":{},\"increment\":{}",
+            kind("While"),
+            self.expr(condition),
+            self.stmt(body),
+            self.optional_expr(increment)
+        )
+    }
+
+    fn visit_break(&self, _keyword: &Token) -> String {
+        kind("Break")
+    }
+
+    fn visit_continue(&self, _keyword: &Token) -> String {
+        kind("Continue")
+    }
+
+    fn visit_function(&self, declaration: &Rc<FunctionData>) -> String {
+        self.function(declaration)
+    }
+
+    fn visit_return(&self, _keyword: &Token, value: Option<&Expr>) -> String {
+        format!("{},\"value\":{}", kind("Return"), self.optional_expr(value))
+    }
+
+    fn visit_class(
+        &self,
+        name: &Token,
+        superclass: Option<&VariableData>,
+        methods: &[Rc<FunctionData>],
+    ) -> String {
+        let superclass = superclass.map_or_else(
+            || "null".to_string(),
+            |superclass| json::string(&superclass.name.lexeme),
+        );
+        let methods: Vec<String> = methods
+            .iter()
+            .map(|method| format!("{{{}}}", self.function(method)))
+            .collect();
+        format!(
+            "{},\"name\":{},\"superclass\":{superclass},\"methods\":[{}]",
+            kind("Class"),
+            json::string(&name.lexeme),
+            methods.join(",")
+        )
+    }
+}
@@ -1,4 +1,11 @@
-use crate::{expr::{Expr, ExpressionVisitor}, literal::Literal, token::{Token}};
+use std::rc::Rc;
+
+use crate::{
+    expr::{Expr, ExpressionVisitor, ScopeDepth, VariableData},
+    literal::Literal,
+    stmt::{FunctionData, StatementVisitor, Stmt},
+    token::Token,
+};
 
 pub struct AstPrinter;
 
@@ -7,6 +14,31 @@ impl AstPrinter {
         expr.accept(self)
     }
 
+    /// Prints each statement of a program on its own line.
+    pub fn print_program(&self, statements: &[Stmt]) -> String {
+        let mut output = String::new();
+        for statement in statements {
+            output += &statement.accept(self);
+            output += "\n";
+        }
+        output
+    }
+
+    fn function(&self, keyword: &str, declaration: &FunctionData) -> String {
+        let params: Vec<&str> = declaration
+            .params
+            .iter()
+            .map(|param| param.lexeme.as_str())
+            .collect();
+        let mut output = format!("({keyword} {} ({})", declaration.name.lexeme, params.join(" "));
+        for statement in &declaration.body {
+            output += " ";
+            output += &statement.accept(self);
+        }
+        output += ")";
+        output
+    }
+
     fn parenthesize(&self, name: &str, exprs: &[&Expr]) -> String{
         let mut output = String::new();
     
@@ -20,7 +52,7 @@ impl AstPrinter {
         }
 
         output += ")";
-        return output;
+        output
     }
 }
 
@@ -40,5 +72,127 @@ impl ExpressionVisitor<String> for AstPrinter {
     fn visit_grouping(&self, expr: &Expr) -> String {
         self.parenthesize("group", &[expr])
     }
-    
+
+    fn visit_variable(&self, name: &Token, _depth: &ScopeDepth) -> String {
+        name.lexeme.to_string()
+    }
+
+    fn visit_assign(&self, name: &Token, value: &Expr, _depth: &ScopeDepth) -> String {
+        self.parenthesize(&format!("= {}", name.lexeme), &[value])
+    }
+
+    fn visit_logical(&self, left: &Expr, operator: &Token, right: &Expr) -> String {
+        self.parenthesize(&operator.lexeme, &[left, right])
+    }
+
+    fn visit_conditional(&self, condition: &Expr, then_branch: &Expr, else_branch: &Expr) -> String {
+        self.parenthesize("?:", &[condition, then_branch, else_branch])
+    }
+
+    fn visit_call(&self, callee: &Expr, _paren: &Token, arguments: &[Expr]) -> String {
+        let mut exprs = vec![callee];
+        exprs.extend(arguments);
+        self.parenthesize("call", &exprs)
+    }
+
+    fn visit_get(&self, object: &Expr, name: &Token) -> String {
+        self.parenthesize(&format!(". {}", name.lexeme), &[object])
+    }
+
+    fn visit_set(&self, object: &Expr, name: &Token, value: &Expr) -> String {
+        self.parenthesize(&format!("= .{}", name.lexeme), &[object, value])
+    }
+
+    fn visit_this(&self, keyword: &Token, _depth: &ScopeDepth) -> String {
+        keyword.lexeme.to_string()
+    }
+
+    fn visit_super(&self, keyword: &Token, method: &Token, _depth: &ScopeDepth) -> String {
+        format!("({} {})", keyword.lexeme, method.lexeme)
+    }
+}
+
+impl StatementVisitor<String> for AstPrinter {
+    fn visit_expression(&self, expr: &Expr) -> String {
+        self.parenthesize(";", &[expr])
+    }
+
+    fn visit_print(&self, expr: &Expr) -> String {
+        self.parenthesize("print", &[expr])
+    }
+
+    fn visit_var(&self, name: &Token, initializer: Option<&Expr>) -> String {
+        let name = format!("var {}", name.lexeme);
+        match initializer {
+            Some(initializer) => self.parenthesize(&name, &[initializer]),
+            None => self.parenthesize(&name, &[]),
+        }
+    }
+
+    fn visit_block(&self, statements: &[Stmt]) -> String {
+        let mut output = String::from("(block");
+        for statement in statements {
+            output += " ";
+            output += &statement.accept(self);
+        }
+        output += ")";
+        output
+    }
+
+    fn visit_if(&self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>) -> String {
+        let mut output = format!("(if {} {}", condition.accept(self), then_branch.accept(self));
+        if let Some(else_branch) = else_branch {
+            output += " ";
+            output += &else_branch.accept(self);
+        }
+        output += ")";
+        output
+    }
+
+    fn visit_while(&self, condition: &Expr, body: &Stmt, increment: Option<&Expr>) -> String {
+        let mut output = format!("(while {} {}", condition.accept(self), body.accept(self));
+        if let Some(increment) = increment {
+            output += " ";
+            output += &increment.accept(self);
+        }
+        output += ")";
+        output
+    }
+
+    fn visit_break(&self, _keyword: &Token) -> String {
+        "(break)".to_string()
+    }
+
+    fn visit_continue(&self, _keyword: &Token) -> String {
+        "(continue)".to_string()
+    }
+
+    fn visit_function(&self, declaration: &Rc<FunctionData>) -> String {
+        self.function("fun", declaration)
+    }
+
+    fn visit_return(&self, _keyword: &Token, value: Option<&Expr>) -> String {
+        match value {
+            Some(value) => self.parenthesize("return", &[value]),
+            None => self.parenthesize("return", &[]),
+        }
+    }
+
+    fn visit_class(
+        &self,
+        name: &Token,
+        superclass: Option<&VariableData>,
+        methods: &[Rc<FunctionData>],
+    ) -> String {
+        let mut output = format!("(class {}", name.lexeme);
+        if let Some(superclass) = superclass {
+            output += &format!(" < {}", superclass.name.lexeme);
+        }
+        for method in methods {
+            output += " ";
+            output += &self.function("method", method);
+        }
+        output += ")";
+        output
+    }
 }
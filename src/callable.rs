@@ -0,0 +1,104 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::{
+    environment::Environment,
+    interpreter::{Interpreter, InterpreterError, Unwind},
+    literal::Literal,
+    object::Object,
+    stmt::FunctionData,
+    symbol::Symbol,
+};
+
+pub trait LoxCallable {
+    fn arity(&self) -> usize;
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Object>) -> Result<Object, InterpreterError>;
+}
+
+pub struct LoxFunction {
+    declaration: Rc<FunctionData>,
+    closure: Rc<RefCell<Environment>>,
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub fn new(
+        declaration: Rc<FunctionData>,
+        closure: Rc<RefCell<Environment>>,
+        is_initializer: bool,
+    ) -> Self {
+        Self {
+            declaration,
+            closure,
+            is_initializer,
+        }
+    }
+
+    /// Returns a copy of this method whose closure binds `this` to `instance`.
+    pub fn bind(&self, instance: Object) -> LoxFunction {
+        let mut environment = Environment::with_enclosing(self.closure.clone());
+        environment.define(Symbol::intern("this"), instance);
+        LoxFunction::new(
+            self.declaration.clone(),
+            Rc::new(RefCell::new(environment)),
+            self.is_initializer,
+        )
+    }
+
+    fn this(&self) -> Object {
+        self.closure
+            .borrow()
+            .get_at(0, Symbol::intern("this"))
+            .expect("Compiler error: initializer is not bound to an instance")
+    }
+}
+
+impl LoxCallable for LoxFunction {
+    fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Object>) -> Result<Object, InterpreterError> {
+        let mut environment = Environment::with_enclosing(self.closure.clone());
+        for (param, argument) in self.declaration.params.iter().zip(arguments) {
+            environment.define(param.lexeme, argument);
+        }
+
+        // Initializers always return `this`, even from an early bare `return`.
+        match interpreter.execute_block(&self.declaration.body, Rc::new(RefCell::new(environment))) {
+            Ok(()) | Err(Unwind::Return(_)) if self.is_initializer => Ok(self.this()),
+            Ok(()) => Ok(Object::Literal(Literal::Nil)),
+            Err(Unwind::Return(value)) => Ok(value),
+            Err(Unwind::Error(e)) => Err(e),
+            Err(Unwind::Break | Unwind::Continue) => {
+                unreachable!("Compiler error: loop control escaped its function")
+            }
+        }
+    }
+}
+
+impl fmt::Display for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.declaration.name.lexeme)
+    }
+}
+
+pub struct NativeFunction {
+    pub arity: usize,
+    pub function: fn(&[Object]) -> Object,
+}
+
+impl LoxCallable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Object>) -> Result<Object, InterpreterError> {
+        Ok((self.function)(&arguments))
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn>")
+    }
+}
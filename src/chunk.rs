@@ -0,0 +1,175 @@
+use std::rc::Rc;
+
+use crate::{symbol::Symbol, token::Span};
+
+/// A bytecode instruction. Operands follow the opcode byte in the chunk;
+/// the comment on each variant lists them. Constant and name operands are
+/// two-byte big-endian indexes into the constant pool.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum OpCode {
+    /// constant: u16, a number or string
+    Constant,
+    Nil,
+    True,
+    False,
+    Pop,
+    /// slot: u8
+    GetLocal,
+    /// slot: u8
+    SetLocal,
+    /// name: u16
+    GetGlobal,
+    /// name: u16
+    DefineGlobal,
+    /// name: u16
+    SetGlobal,
+    /// index: u8
+    GetUpvalue,
+    /// index: u8
+    SetUpvalue,
+    /// name: u16
+    GetProperty,
+    /// name: u16
+    SetProperty,
+    /// name: u16
+    GetSuper,
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Not,
+    Negate,
+    Print,
+    /// offset: u16, forwards
+    Jump,
+    /// offset: u16, forwards
+    JumpIfFalse,
+    /// offset: u16, backwards
+    Loop,
+    /// argument count: u8
+    Call,
+    /// function: u16, then an `is_local: u8, index: u8` pair per upvalue
+    Closure,
+    CloseUpvalue,
+    Return,
+    /// name: u16
+    Class,
+    Inherit,
+    /// name: u16
+    Method,
+}
+
+impl OpCode {
+    const ALL: [OpCode; 38] = [
+        OpCode::Constant,
+        OpCode::Nil,
+        OpCode::True,
+        OpCode::False,
+        OpCode::Pop,
+        OpCode::GetLocal,
+        OpCode::SetLocal,
+        OpCode::GetGlobal,
+        OpCode::DefineGlobal,
+        OpCode::SetGlobal,
+        OpCode::GetUpvalue,
+        OpCode::SetUpvalue,
+        OpCode::GetProperty,
+        OpCode::SetProperty,
+        OpCode::GetSuper,
+        OpCode::Equal,
+        OpCode::NotEqual,
+        OpCode::Greater,
+        OpCode::GreaterEqual,
+        OpCode::Less,
+        OpCode::LessEqual,
+        OpCode::Add,
+        OpCode::Subtract,
+        OpCode::Multiply,
+        OpCode::Divide,
+        OpCode::Not,
+        OpCode::Negate,
+        OpCode::Print,
+        OpCode::Jump,
+        OpCode::JumpIfFalse,
+        OpCode::Loop,
+        OpCode::Call,
+        OpCode::Closure,
+        OpCode::CloseUpvalue,
+        OpCode::Return,
+        OpCode::Class,
+        OpCode::Inherit,
+        OpCode::Method,
+    ];
+
+    pub fn from_byte(byte: u8) -> Option<OpCode> {
+        Self::ALL.get(byte as usize).copied()
+    }
+}
+
+/// A value known at compile time.
+#[derive(Debug, Clone)]
+pub enum Constant {
+    Number(f64),
+    String(Rc<str>),
+    /// The name of a variable, property, method or class.
+    Name(Symbol),
+    Function(Rc<Function>),
+}
+
+/// A compiled function body. Closures created at runtime share it.
+#[derive(Debug)]
+pub struct Function {
+    pub name: Rc<str>,
+    pub arity: usize,
+    pub upvalue_count: usize,
+    pub chunk: Chunk,
+}
+
+/// A sequence of bytecode with its constant pool and a table mapping code
+/// offsets back to the source they were compiled from.
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<u8>,
+    pub constants: Vec<Constant>,
+    /// `(offset, span)` pairs sorted by offset. Each span covers the code from
+    /// its offset up to the next entry, so runs of bytes compiled from the
+    /// same token share one entry.
+    pub spans: Vec<(usize, Span)>,
+}
+
+impl Chunk {
+    pub fn write(&mut self, byte: u8, span: Span) {
+        if self.spans.last().is_none_or(|&(_, last)| last != span) {
+            self.spans.push((self.code.len(), span));
+        }
+        self.code.push(byte);
+    }
+
+    pub fn write_op(&mut self, op: OpCode, span: Span) {
+        self.write(op as u8, span);
+    }
+
+    pub fn add_constant(&mut self, constant: Constant) -> usize {
+        self.constants.push(constant);
+        self.constants.len() - 1
+    }
+
+    pub fn read_u16(&self, offset: usize) -> u16 {
+        u16::from_be_bytes([self.code[offset], self.code[offset + 1]])
+    }
+
+    /// The span of source code the byte at `offset` was compiled from.
+    pub fn span(&self, offset: usize) -> Span {
+        let index = self.spans.partition_point(|&(start, _)| start <= offset);
+        index
+            .checked_sub(1)
+            .map_or_else(Span::default, |index| self.spans[index].1)
+    }
+}
@@ -0,0 +1,98 @@
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
+
+use crate::{
+    callable::{LoxCallable, LoxFunction},
+    interpreter::{Interpreter, InterpreterError},
+    object::Object,
+    symbol::Symbol,
+    token::Token,
+};
+
+pub struct LoxClass {
+    pub name: String,
+    superclass: Option<Rc<LoxClass>>,
+    methods: HashMap<Symbol, Rc<LoxFunction>>,
+}
+
+impl LoxClass {
+    pub fn new(
+        name: &str,
+        superclass: Option<Rc<LoxClass>>,
+        methods: HashMap<Symbol, Rc<LoxFunction>>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            superclass,
+            methods,
+        }
+    }
+
+    /// Looks a method up on this class, then up the superclass chain.
+    pub fn find_method(&self, name: Symbol) -> Option<Rc<LoxFunction>> {
+        self.methods.get(&name).cloned().or_else(|| {
+            self.superclass
+                .as_ref()
+                .and_then(|superclass| superclass.find_method(name))
+        })
+    }
+}
+
+// Implemented on the `Rc` because every instance keeps a handle to its class.
+impl LoxCallable for Rc<LoxClass> {
+    fn arity(&self) -> usize {
+        self.find_method(Symbol::intern("init")).map_or(0, |initializer| initializer.arity())
+    }
+
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Object>) -> Result<Object, InterpreterError> {
+        let instance = Object::Instance(Rc::new(RefCell::new(LoxInstance::new(self.clone()))));
+        if let Some(initializer) = self.find_method(Symbol::intern("init")) {
+            initializer.bind(instance.clone()).call(interpreter, arguments)?;
+        }
+        Ok(instance)
+    }
+}
+
+impl fmt::Display for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+pub struct LoxInstance {
+    class: Rc<LoxClass>,
+    fields: HashMap<Symbol, Object>,
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>) -> Self {
+        Self {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    /// Reads a field, falling back to a method bound to `instance`.
+    pub fn get(instance: &Rc<RefCell<LoxInstance>>, name: &Token) -> Result<Object, InterpreterError> {
+        let this = instance.borrow();
+        if let Some(value) = this.fields.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        match this.class.find_method(name.lexeme) {
+            Some(method) => Ok(Object::Function(Rc::new(
+                method.bind(Object::Instance(instance.clone())),
+            ))),
+            None => Err(InterpreterError::UndefinedProperty { name: name.clone() }),
+        }
+    }
+
+    pub fn set(&mut self, name: &Token, value: Object) {
+        self.fields.insert(name.lexeme, value);
+    }
+}
+
+impl fmt::Display for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
@@ -0,0 +1,772 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use thiserror::Error;
+
+use crate::{
+    chunk::{Chunk, Constant, Function, OpCode},
+    diagnostic::Diagnostic,
+    expr::{Expr, ExpressionVisitor, ScopeDepth, VariableData},
+    literal::Literal,
+    stmt::{FunctionData, StatementVisitor, Stmt},
+    symbol::Symbol,
+    token::{Span, Token, TokenType},
+};
+
+#[derive(Error, Debug)]
+pub enum CompilerError {
+    #[error("Too many constants in one chunk.")]
+    TooManyConstants { span: Span },
+
+    #[error("Too many local variables in function.")]
+    TooManyLocals { span: Span },
+
+    #[error("Too many closure variables in function.")]
+    TooManyUpvalues { span: Span },
+
+    #[error("Too much code to jump over.")]
+    JumpTooLarge { span: Span },
+
+    #[error("Loop body too large.")]
+    LoopTooLarge { span: Span },
+}
+
+impl CompilerError {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::TooManyConstants { span }
+            | Self::TooManyLocals { span }
+            | Self::TooManyUpvalues { span }
+            | Self::JumpTooLarge { span }
+            | Self::LoopTooLarge { span } => *span,
+        }
+    }
+
+    /// A stable identifier for the kind of error, used in JSON diagnostics.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::TooManyConstants { .. } => "E0500",
+            Self::TooManyLocals { .. } => "E0501",
+            Self::TooManyUpvalues { .. } => "E0502",
+            Self::JumpTooLarge { .. } => "E0503",
+            Self::LoopTooLarge { .. } => "E0504",
+        }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::error(self.code(), &self.to_string(), self.span())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionKind {
+    Script,
+    Function,
+    Method,
+    Initializer,
+}
+
+struct Local {
+    name: Symbol,
+    depth: usize,
+    /// Set when a closure captures the variable, so leaving its scope has to
+    /// move it off the stack.
+    is_captured: bool,
+}
+
+struct UpvalueRef {
+    index: u8,
+    is_local: bool,
+}
+
+struct Loop {
+    /// The scope depth outside the loop body. Jumping out of the body
+    /// discards every local declared deeper than this.
+    scope_depth: usize,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+/// The function currently being compiled. Nested function declarations push
+/// a new one.
+struct FunctionState {
+    name: Rc<str>,
+    kind: FunctionKind,
+    arity: usize,
+    chunk: Chunk,
+    locals: Vec<Local>,
+    upvalues: Vec<UpvalueRef>,
+    scope_depth: usize,
+    loops: Vec<Loop>,
+}
+
+impl FunctionState {
+    fn new(name: &str, kind: FunctionKind) -> Self {
+        // Slot zero holds the closure being called, or the receiver in methods.
+        let slot_zero = match kind {
+            FunctionKind::Method | FunctionKind::Initializer => "this",
+            FunctionKind::Script | FunctionKind::Function => "",
+        };
+        Self {
+            name: name.into(),
+            kind,
+            arity: 0,
+            chunk: Chunk::default(),
+            locals: vec![Local {
+                name: Symbol::intern(slot_zero),
+                depth: 0,
+                is_captured: false,
+            }],
+            upvalues: Vec::new(),
+            scope_depth: 0,
+            loops: Vec::new(),
+        }
+    }
+
+    fn resolve_local(&self, name: Symbol) -> Option<u8> {
+        self.locals
+            .iter()
+            .rposition(|local| local.name == name)
+            .map(|slot| slot as u8)
+    }
+
+    fn add_upvalue(&mut self, index: u8, is_local: bool) -> Option<u8> {
+        if let Some(existing) = self
+            .upvalues
+            .iter()
+            .position(|upvalue| upvalue.index == index && upvalue.is_local == is_local)
+        {
+            return Some(existing as u8);
+        }
+        if self.upvalues.len() == u8::MAX as usize + 1 {
+            return None;
+        }
+        self.upvalues.push(UpvalueRef { index, is_local });
+        Some((self.upvalues.len() - 1) as u8)
+    }
+}
+
+/// Where a variable reference resolves to.
+enum Resolved {
+    Local(u8),
+    Upvalue(u8),
+    Global,
+}
+
+/// Compiles a resolved program into bytecode for the [`Vm`].
+///
+/// [`Vm`]: crate::vm::Vm
+pub struct Compiler {
+    states: RefCell<Vec<FunctionState>>,
+    /// The span of the expression being compiled, for nodes whose visitor
+    /// doesn't get a token.
+    span: Cell<Span>,
+    errors: RefCell<Vec<CompilerError>>,
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Self {
+            states: RefCell::new(vec![FunctionState::new("script", FunctionKind::Script)]),
+            span: Cell::new(Span::default()),
+            errors: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn compile(self, statements: &[Stmt]) -> Result<Rc<Function>, Vec<CompilerError>> {
+        for statement in statements {
+            statement.accept(&self);
+        }
+        self.emit_return(self.span.get());
+
+        let errors = self.errors.into_inner();
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        let state = self.states.into_inner().pop().expect("script state");
+        Ok(Rc::new(Function {
+            name: state.name,
+            arity: 0,
+            upvalue_count: 0,
+            chunk: state.chunk,
+        }))
+    }
+
+    fn compile_expr(&self, expr: &Expr) {
+        self.span.set(expr.span());
+        expr.accept(self);
+    }
+
+    fn error(&self, error: CompilerError) {
+        self.errors.borrow_mut().push(error);
+    }
+
+    fn with_state<T>(&self, f: impl FnOnce(&mut FunctionState) -> T) -> T {
+        f(self.states.borrow_mut().last_mut().expect("function state"))
+    }
+
+    fn code_len(&self) -> usize {
+        self.with_state(|state| state.chunk.code.len())
+    }
+
+    fn emit(&self, op: OpCode, span: Span) {
+        self.with_state(|state| state.chunk.write_op(op, span));
+    }
+
+    fn emit_byte(&self, byte: u8, span: Span) {
+        self.with_state(|state| state.chunk.write(byte, span));
+    }
+
+    fn emit_u16(&self, value: u16, span: Span) {
+        for byte in value.to_be_bytes() {
+            self.emit_byte(byte, span);
+        }
+    }
+
+    fn emit_with_u16(&self, op: OpCode, operand: u16, span: Span) {
+        self.emit(op, span);
+        self.emit_u16(operand, span);
+    }
+
+    fn emit_return(&self, span: Span) {
+        if self.with_state(|state| state.kind) == FunctionKind::Initializer {
+            self.emit(OpCode::GetLocal, span);
+            self.emit_byte(0, span);
+        } else {
+            self.emit(OpCode::Nil, span);
+        }
+        self.emit(OpCode::Return, span);
+    }
+
+    fn make_constant(&self, constant: Constant, span: Span) -> u16 {
+        let index = self.with_state(|state| {
+            // Reuse strings and names so repeated ones don't fill the pool.
+            let existing = state
+                .chunk
+                .constants
+                .iter()
+                .position(|existing| match (existing, &constant) {
+                    (Constant::String(l), Constant::String(r)) => l == r,
+                    (Constant::Name(l), Constant::Name(r)) => l == r,
+                    _ => false,
+                });
+            if let Some(existing) = existing {
+                return existing;
+            }
+            state.chunk.add_constant(constant)
+        });
+        u16::try_from(index).unwrap_or_else(|_| {
+            self.error(CompilerError::TooManyConstants { span });
+            0
+        })
+    }
+
+    fn identifier_constant(&self, name: &Token) -> u16 {
+        self.make_constant(Constant::Name(name.lexeme), name.span)
+    }
+
+    fn emit_jump(&self, op: OpCode, span: Span) -> usize {
+        self.emit_with_u16(op, u16::MAX, span);
+        self.code_len() - 2
+    }
+
+    fn patch_jump(&self, offset: usize, span: Span) {
+        let jump = self.code_len() - offset - 2;
+        let Ok(jump) = u16::try_from(jump) else {
+            self.error(CompilerError::JumpTooLarge { span });
+            return;
+        };
+        self.with_state(|state| {
+            state.chunk.code[offset..offset + 2].copy_from_slice(&jump.to_be_bytes());
+        });
+    }
+
+    fn emit_loop(&self, start: usize, span: Span) {
+        self.emit(OpCode::Loop, span);
+        let offset = self.code_len() - start + 2;
+        let offset = u16::try_from(offset).unwrap_or_else(|_| {
+            self.error(CompilerError::LoopTooLarge { span });
+            0
+        });
+        self.emit_u16(offset, span);
+    }
+
+    fn begin_scope(&self) {
+        self.with_state(|state| state.scope_depth += 1);
+    }
+
+    fn end_scope(&self, span: Span) {
+        let captured: Vec<bool> = self.with_state(|state| {
+            state.scope_depth -= 1;
+            let depth = state.scope_depth;
+            let keep = state
+                .locals
+                .iter()
+                .rposition(|local| local.depth <= depth)
+                .map_or(0, |index| index + 1);
+            state
+                .locals
+                .drain(keep..)
+                .rev()
+                .map(|local| local.is_captured)
+                .collect()
+        });
+        for is_captured in captured {
+            self.emit_pop_local(is_captured, span);
+        }
+    }
+
+    fn emit_pop_local(&self, is_captured: bool, span: Span) {
+        if is_captured {
+            self.emit(OpCode::CloseUpvalue, span);
+        } else {
+            self.emit(OpCode::Pop, span);
+        }
+    }
+
+    fn is_global_scope(&self) -> bool {
+        self.with_state(|state| state.scope_depth == 0)
+    }
+
+    fn add_local(&self, name: Symbol, span: Span) {
+        let added = self.with_state(|state| {
+            if state.locals.len() == u8::MAX as usize + 1 {
+                return false;
+            }
+            let depth = state.scope_depth;
+            state.locals.push(Local {
+                name,
+                depth,
+                is_captured: false,
+            });
+            true
+        });
+        if !added {
+            self.error(CompilerError::TooManyLocals { span });
+        }
+    }
+
+    fn resolve(&self, name: &Token) -> Resolved {
+        let mut states = self.states.borrow_mut();
+        if let Some(slot) = states.last().and_then(|s| s.resolve_local(name.lexeme)) {
+            return Resolved::Local(slot);
+        }
+        match resolve_upvalue(&mut states, name.lexeme) {
+            Ok(Some(index)) => Resolved::Upvalue(index),
+            Ok(None) => Resolved::Global,
+            Err(()) => {
+                self.error(CompilerError::TooManyUpvalues { span: name.span });
+                Resolved::Upvalue(0)
+            }
+        }
+    }
+
+    fn get_variable(&self, name: &Token) {
+        match self.resolve(name) {
+            Resolved::Local(slot) => {
+                self.emit(OpCode::GetLocal, name.span);
+                self.emit_byte(slot, name.span);
+            }
+            Resolved::Upvalue(index) => {
+                self.emit(OpCode::GetUpvalue, name.span);
+                self.emit_byte(index, name.span);
+            }
+            Resolved::Global => {
+                let constant = self.identifier_constant(name);
+                self.emit_with_u16(OpCode::GetGlobal, constant, name.span);
+            }
+        }
+    }
+
+    fn set_variable(&self, name: &Token) {
+        match self.resolve(name) {
+            Resolved::Local(slot) => {
+                self.emit(OpCode::SetLocal, name.span);
+                self.emit_byte(slot, name.span);
+            }
+            Resolved::Upvalue(index) => {
+                self.emit(OpCode::SetUpvalue, name.span);
+                self.emit_byte(index, name.span);
+            }
+            Resolved::Global => {
+                let constant = self.identifier_constant(name);
+                self.emit_with_u16(OpCode::SetGlobal, constant, name.span);
+            }
+        }
+    }
+
+    /// Binds the value on top of the stack to `name` in the current scope.
+    /// Locals must already have been added with [`Compiler::add_local`].
+    fn define_variable(&self, name: &Token) {
+        if self.is_global_scope() {
+            let constant = self.identifier_constant(name);
+            self.emit_with_u16(OpCode::DefineGlobal, constant, name.span);
+        }
+    }
+
+    fn function(&self, declaration: &FunctionData, kind: FunctionKind) {
+        let span = declaration.name.span;
+        let mut state = FunctionState::new(&declaration.name.lexeme, kind);
+        state.scope_depth = 1;
+        state.arity = declaration.params.len();
+        self.states.borrow_mut().push(state);
+
+        for param in &declaration.params {
+            self.add_local(param.lexeme, param.span);
+        }
+        for statement in &declaration.body {
+            statement.accept(self);
+        }
+        self.emit_return(span);
+
+        let state = self.states.borrow_mut().pop().expect("function state");
+        let function = Function {
+            name: state.name,
+            arity: state.arity,
+            upvalue_count: state.upvalues.len(),
+            chunk: state.chunk,
+        };
+        let constant = self.make_constant(Constant::Function(Rc::new(function)), span);
+        self.emit_with_u16(OpCode::Closure, constant, span);
+        for upvalue in state.upvalues {
+            self.emit_byte(upvalue.is_local as u8, span);
+            self.emit_byte(upvalue.index, span);
+        }
+    }
+
+    /// Discards the locals a `break` or `continue` jumps out of, without
+    /// forgetting them since compilation carries on inside the loop body.
+    fn exit_loop_body(&self, span: Span) {
+        let captured: Vec<bool> = self.with_state(|state| {
+            let depth = state.loops.last().expect("loop").scope_depth;
+            state
+                .locals
+                .iter()
+                .rev()
+                .take_while(|local| local.depth > depth)
+                .map(|local| local.is_captured)
+                .collect()
+        });
+        for is_captured in captured {
+            self.emit_pop_local(is_captured, span);
+        }
+    }
+}
+
+/// Finds `name` in the functions enclosing the last one in `states`,
+/// threading it through each function in between as an upvalue. Fails if a
+/// function runs out of upvalue slots.
+fn resolve_upvalue(states: &mut [FunctionState], name: Symbol) -> Result<Option<u8>, ()> {
+    let Some((current, enclosing)) = states.split_last_mut() else {
+        return Ok(None);
+    };
+    let Some(parent) = enclosing.last_mut() else {
+        return Ok(None);
+    };
+
+    if let Some(slot) = parent.resolve_local(name) {
+        parent.locals[slot as usize].is_captured = true;
+        return current.add_upvalue(slot, true).map(Some).ok_or(());
+    }
+
+    match resolve_upvalue(enclosing, name)? {
+        Some(index) => current.add_upvalue(index, false).map(Some).ok_or(()),
+        None => Ok(None),
+    }
+}
+
+impl ExpressionVisitor<()> for Compiler {
+    fn visit_binary(&self, left: &Expr, operand: &Token, right: &Expr) {
+        self.compile_expr(left);
+        if operand.ttype == TokenType::Comma {
+            self.emit(OpCode::Pop, operand.span);
+            self.compile_expr(right);
+            return;
+        }
+        self.compile_expr(right);
+
+        let op = match operand.ttype {
+            TokenType::Plus => OpCode::Add,
+            TokenType::Minus => OpCode::Subtract,
+            TokenType::Star => OpCode::Multiply,
+            TokenType::Slash => OpCode::Divide,
+            TokenType::Greater => OpCode::Greater,
+            TokenType::GreaterEqual => OpCode::GreaterEqual,
+            TokenType::Less => OpCode::Less,
+            TokenType::LessEqual => OpCode::LessEqual,
+            TokenType::EqualEqual => OpCode::Equal,
+            TokenType::BangEqual => OpCode::NotEqual,
+            _ => unreachable!(
+                "Compiler error: invalid binary operator {:?}",
+                operand.ttype
+            ),
+        };
+        self.emit(op, operand.span);
+    }
+
+    fn visit_unary(&self, operand: &Token, expr: &Expr) {
+        self.compile_expr(expr);
+        let op = match operand.ttype {
+            TokenType::Minus => OpCode::Negate,
+            TokenType::Bang => OpCode::Not,
+            _ => unreachable!("Compiler error: invalid unary operator {:?}", operand.ttype),
+        };
+        self.emit(op, operand.span);
+    }
+
+    fn visit_literal(&self, literal: &Literal) {
+        let span = self.span.get();
+        match literal {
+            Literal::Number(number) => {
+                let constant = self.make_constant(Constant::Number(*number), span);
+                self.emit_with_u16(OpCode::Constant, constant, span);
+            }
+            Literal::String(string) => {
+                let constant = self.make_constant(Constant::String(string.as_str().into()), span);
+                self.emit_with_u16(OpCode::Constant, constant, span);
+            }
+            Literal::Bool(true) => self.emit(OpCode::True, span),
+            Literal::Bool(false) => self.emit(OpCode::False, span),
+            Literal::Nil => self.emit(OpCode::Nil, span),
+        }
+    }
+
+    fn visit_grouping(&self, expr: &Expr) {
+        self.compile_expr(expr);
+    }
+
+    fn visit_variable(&self, name: &Token, _depth: &ScopeDepth) {
+        self.get_variable(name);
+    }
+
+    fn visit_assign(&self, name: &Token, value: &Expr, _depth: &ScopeDepth) {
+        self.compile_expr(value);
+        self.set_variable(name);
+    }
+
+    fn visit_logical(&self, left: &Expr, operator: &Token, right: &Expr) {
+        self.compile_expr(left);
+        let span = operator.span;
+        match operator.ttype {
+            TokenType::And => {
+                let end = self.emit_jump(OpCode::JumpIfFalse, span);
+                self.emit(OpCode::Pop, span);
+                self.compile_expr(right);
+                self.patch_jump(end, span);
+            }
+            TokenType::Or => {
+                let else_jump = self.emit_jump(OpCode::JumpIfFalse, span);
+                let end = self.emit_jump(OpCode::Jump, span);
+                self.patch_jump(else_jump, span);
+                self.emit(OpCode::Pop, span);
+                self.compile_expr(right);
+                self.patch_jump(end, span);
+            }
+            _ => unreachable!(
+                "Compiler error: invalid logical operator {:?}",
+                operator.ttype
+            ),
+        }
+    }
+
+    fn visit_conditional(&self, condition: &Expr, then_branch: &Expr, else_branch: &Expr) {
+        let span = condition.span();
+        self.compile_expr(condition);
+        let else_jump = self.emit_jump(OpCode::JumpIfFalse, span);
+        self.emit(OpCode::Pop, span);
+        self.compile_expr(then_branch);
+        let end = self.emit_jump(OpCode::Jump, span);
+        self.patch_jump(else_jump, span);
+        self.emit(OpCode::Pop, span);
+        self.compile_expr(else_branch);
+        self.patch_jump(end, span);
+    }
+
+    fn visit_call(&self, callee: &Expr, paren: &Token, arguments: &[Expr]) {
+        self.compile_expr(callee);
+        for argument in arguments {
+            self.compile_expr(argument);
+        }
+        // The parser already limits calls to 255 arguments.
+        self.emit(OpCode::Call, paren.span);
+        self.emit_byte(arguments.len() as u8, paren.span);
+    }
+
+    fn visit_get(&self, object: &Expr, name: &Token) {
+        self.compile_expr(object);
+        let constant = self.identifier_constant(name);
+        self.emit_with_u16(OpCode::GetProperty, constant, name.span);
+    }
+
+    fn visit_set(&self, object: &Expr, name: &Token, value: &Expr) {
+        self.compile_expr(object);
+        self.compile_expr(value);
+        let constant = self.identifier_constant(name);
+        self.emit_with_u16(OpCode::SetProperty, constant, name.span);
+    }
+
+    fn visit_this(&self, keyword: &Token, _depth: &ScopeDepth) {
+        self.get_variable(keyword);
+    }
+
+    fn visit_super(&self, keyword: &Token, method: &Token, _depth: &ScopeDepth) {
+        self.get_variable(&Token::new(TokenType::This, "this", keyword.span));
+        self.get_variable(keyword);
+        let constant = self.identifier_constant(method);
+        self.emit_with_u16(OpCode::GetSuper, constant, method.span);
+    }
+}
+
+impl StatementVisitor<()> for Compiler {
+    fn visit_expression(&self, expr: &Expr) {
+        self.compile_expr(expr);
+        self.emit(OpCode::Pop, expr.span());
+    }
+
+    fn visit_print(&self, expr: &Expr) {
+        self.compile_expr(expr);
+        self.emit(OpCode::Print, expr.span());
+    }
+
+    fn visit_var(&self, name: &Token, initializer: Option<&Expr>) {
+        match initializer {
+            Some(initializer) => self.compile_expr(initializer),
+            None => self.emit(OpCode::Nil, name.span),
+        }
+        if !self.is_global_scope() {
+            self.add_local(name.lexeme, name.span);
+        }
+        self.define_variable(name);
+    }
+
+    fn visit_block(&self, statements: &[Stmt]) {
+        self.begin_scope();
+        for statement in statements {
+            statement.accept(self);
+        }
+        self.end_scope(self.span.get());
+    }
+
+    fn visit_if(&self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>) {
+        let span = condition.span();
+        self.compile_expr(condition);
+        let then_jump = self.emit_jump(OpCode::JumpIfFalse, span);
+        self.emit(OpCode::Pop, span);
+        then_branch.accept(self);
+        let else_jump = self.emit_jump(OpCode::Jump, span);
+        self.patch_jump(then_jump, span);
+        self.emit(OpCode::Pop, span);
+        if let Some(else_branch) = else_branch {
+            else_branch.accept(self);
+        }
+        self.patch_jump(else_jump, span);
+    }
+
+    fn visit_while(&self, condition: &Expr, body: &Stmt, increment: Option<&Expr>) {
+        let span = condition.span();
+        let start = self.code_len();
+        self.compile_expr(condition);
+        let exit = self.emit_jump(OpCode::JumpIfFalse, span);
+        self.emit(OpCode::Pop, span);
+
+        self.with_state(|state| {
+            let scope_depth = state.scope_depth;
+            state.loops.push(Loop {
+                scope_depth,
+                breaks: Vec::new(),
+                continues: Vec::new(),
+            });
+        });
+        body.accept(self);
+        let finished = self.with_state(|state| state.loops.pop().expect("loop"));
+
+        for jump in finished.continues {
+            self.patch_jump(jump, span);
+        }
+        if let Some(increment) = increment {
+            self.compile_expr(increment);
+            self.emit(OpCode::Pop, increment.span());
+        }
+        self.emit_loop(start, span);
+
+        self.patch_jump(exit, span);
+        self.emit(OpCode::Pop, span);
+        for jump in finished.breaks {
+            self.patch_jump(jump, span);
+        }
+    }
+
+    fn visit_break(&self, keyword: &Token) {
+        self.exit_loop_body(keyword.span);
+        let jump = self.emit_jump(OpCode::Jump, keyword.span);
+        self.with_state(|state| state.loops.last_mut().expect("loop").breaks.push(jump));
+    }
+
+    fn visit_continue(&self, keyword: &Token) {
+        self.exit_loop_body(keyword.span);
+        let jump = self.emit_jump(OpCode::Jump, keyword.span);
+        self.with_state(|state| state.loops.last_mut().expect("loop").continues.push(jump));
+    }
+
+    fn visit_function(&self, declaration: &Rc<FunctionData>) {
+        let name = &declaration.name;
+        // Declare the name first so the function can refer to itself.
+        if !self.is_global_scope() {
+            self.add_local(name.lexeme, name.span);
+        }
+        self.function(declaration, FunctionKind::Function);
+        self.define_variable(name);
+    }
+
+    fn visit_return(&self, keyword: &Token, value: Option<&Expr>) {
+        match value {
+            Some(value) => {
+                self.compile_expr(value);
+                self.emit(OpCode::Return, keyword.span);
+            }
+            None => self.emit_return(keyword.span),
+        }
+    }
+
+    fn visit_class(
+        &self,
+        name: &Token,
+        superclass: Option<&VariableData>,
+        methods: &[Rc<FunctionData>],
+    ) {
+        let constant = self.identifier_constant(name);
+        if !self.is_global_scope() {
+            self.add_local(name.lexeme, name.span);
+        }
+        self.emit_with_u16(OpCode::Class, constant, name.span);
+        self.define_variable(name);
+
+        if let Some(superclass) = superclass {
+            self.get_variable(&superclass.name);
+            // Methods close over `super` like any other local.
+            self.begin_scope();
+            self.add_local(Symbol::intern("super"), superclass.name.span);
+            self.get_variable(name);
+            self.emit(OpCode::Inherit, superclass.name.span);
+        }
+
+        self.get_variable(name);
+        for method in methods {
+            let kind = if method.name.lexeme.as_str() == "init" {
+                FunctionKind::Initializer
+            } else {
+                FunctionKind::Method
+            };
+            self.function(method, kind);
+            let constant = self.identifier_constant(&method.name);
+            self.emit_with_u16(OpCode::Method, constant, method.name.span);
+        }
+        self.emit(OpCode::Pop, name.span);
+
+        if superclass.is_some() {
+            self.end_scope(name.span);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::fmt::Write;
+
+use crate::chunk::{Chunk, Constant, Function, OpCode};
+
+/// Disassembles `function` followed by every function nested inside it.
+pub fn disassemble_function(function: &Function) -> String {
+    let mut output = disassemble_chunk(&function.chunk, &function.name);
+    for constant in &function.chunk.constants {
+        if let Constant::Function(nested) = constant {
+            output += "\n";
+            output += &disassemble_function(nested);
+        }
+    }
+    output
+}
+
+pub fn disassemble_chunk(chunk: &Chunk, name: &str) -> String {
+    let mut output = format!("== {name} ==\n");
+    let mut offset = 0;
+    while offset < chunk.code.len() {
+        offset = disassemble_instruction(chunk, offset, &mut output);
+    }
+    output
+}
+
+/// Writes the instruction at `offset` to `output` as one line (plus one per
+/// captured variable for closures) and returns the offset of the next one.
+pub fn disassemble_instruction(chunk: &Chunk, offset: usize, output: &mut String) -> usize {
+    let _ = write!(output, "{offset:04} ");
+    let line = chunk.span(offset).line;
+    if offset > 0 && chunk.span(offset - 1).line == line {
+        output.push_str("   | ");
+    } else {
+        let _ = write!(output, "{line:4} ");
+    }
+
+    let Some(op) = OpCode::from_byte(chunk.code[offset]) else {
+        let _ = writeln!(output, "Unknown opcode {}", chunk.code[offset]);
+        return offset + 1;
+    };
+    let name = format!("{op:?}");
+
+    match op {
+        OpCode::Constant
+        | OpCode::GetGlobal
+        | OpCode::DefineGlobal
+        | OpCode::SetGlobal
+        | OpCode::GetProperty
+        | OpCode::SetProperty
+        | OpCode::GetSuper
+        | OpCode::Class
+        | OpCode::Method => {
+            let index = chunk.read_u16(offset + 1);
+            let _ = writeln!(
+                output,
+                "{name:<16} {index:4} {}",
+                constant(&chunk.constants[index as usize])
+            );
+            offset + 3
+        }
+        OpCode::GetLocal
+        | OpCode::SetLocal
+        | OpCode::GetUpvalue
+        | OpCode::SetUpvalue
+        | OpCode::Call => {
+            let _ = writeln!(output, "{name:<16} {:4}", chunk.code[offset + 1]);
+            offset + 2
+        }
+        OpCode::Jump | OpCode::JumpIfFalse | OpCode::Loop => {
+            let jump = chunk.read_u16(offset + 1) as usize;
+            let target = if op == OpCode::Loop {
+                offset + 3 - jump
+            } else {
+                offset + 3 + jump
+            };
+            let _ = writeln!(output, "{name:<16} {offset:4} -> {target}");
+            offset + 3
+        }
+        OpCode::Closure => {
+            let index = chunk.read_u16(offset + 1);
+            let function = &chunk.constants[index as usize];
+            let _ = writeln!(output, "{name:<16} {index:4} {}", constant(function));
+
+            let upvalue_count = match function {
+                Constant::Function(function) => function.upvalue_count,
+                _ => 0,
+            };
+            let mut next = offset + 3;
+            for _ in 0..upvalue_count {
+                let kind = if chunk.code[next] == 1 {
+                    "local"
+                } else {
+                    "upvalue"
+                };
+                let _ = writeln!(
+                    output,
+                    "{next:04}    |                     {kind} {}",
+                    chunk.code[next + 1]
+                );
+                next += 2;
+            }
+            next
+        }
+        OpCode::Nil
+        | OpCode::True
+        | OpCode::False
+        | OpCode::Pop
+        | OpCode::Equal
+        | OpCode::NotEqual
+        | OpCode::Greater
+        | OpCode::GreaterEqual
+        | OpCode::Less
+        | OpCode::LessEqual
+        | OpCode::Add
+        | OpCode::Subtract
+        | OpCode::Multiply
+        | OpCode::Divide
+        | OpCode::Not
+        | OpCode::Negate
+        | OpCode::Print
+        | OpCode::CloseUpvalue
+        | OpCode::Return
+        | OpCode::Inherit => {
+            let _ = writeln!(output, "{name}");
+            offset + 1
+        }
+    }
+}
+
+fn constant(constant: &Constant) -> String {
+    match constant {
+        Constant::Number(number) => format!("'{number}'"),
+        Constant::String(string) => format!("{string:?}"),
+        Constant::Name(name) => format!("{name:?}"),
+        Constant::Function(function) => format!("<fn {}>", function.name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::{compiler::Compiler, parser::Parser, resolver::Resolver, scanner::Scanner};
+
+    fn compile(source: &str) -> Rc<Function> {
+        let tokens = Scanner::new(source.as_bytes()).scan_tokens().unwrap();
+        let statements = Parser::new(&tokens).parse().unwrap();
+        Resolver::new().resolve(&statements).unwrap();
+        Compiler::new().compile(&statements).unwrap()
+    }
+
+    #[test]
+    fn disassembles_jumps_with_their_targets() {
+        let script = compile(
+            "var n = 0;\n\
+             while (n < 2) n = n + 1;\n\
+             if (n) print \"yes\"; else print \"no\";\n",
+        );
+        let expected = r#"== script ==
+0000    1 Constant            0 '0'
+0003    | DefineGlobal        1 "n"
+0006    2 GetGlobal           1 "n"
+0009    | Constant            2 '2'
+0012    | Less
+0013    | JumpIfFalse        13 -> 31
+0016    | Pop
+0017    | GetGlobal           1 "n"
+0020    | Constant            3 '1'
+0023    | Add
+0024    | SetGlobal           1 "n"
+0027    | Pop
+0028    | Loop               28 -> 6
+0031    | Pop
+0032    3 GetGlobal           1 "n"
+0035    | JumpIfFalse        35 -> 46
+0038    | Pop
+0039    | Constant            4 "yes"
+0042    | Print
+0043    | Jump               43 -> 51
+0046    | Pop
+0047    | Constant            5 "no"
+0050    | Print
+0051    | Nil
+0052    | Return
+"#;
+        assert_eq!(disassemble_function(&script), expected);
+    }
+
+    #[test]
+    fn disassembles_closures_and_their_captures() {
+        let script = compile(
+            "fun outer() {\n\
+               var x = 1;\n\
+               fun middle() {\n\
+                 fun inner() { return x; }\n\
+                 return inner;\n\
+               }\n\
+               return middle;\n\
+             }\n",
+        );
+        let expected = r#"== script ==
+0000    1 Closure             0 <fn outer>
+0003    | DefineGlobal        1 "outer"
+0006    7 Nil
+0007    | Return
+
+== outer ==
+0000    2 Constant            0 '1'
+0003    3 Closure             1 <fn middle>
+0006    |                     local 1
+0008    7 GetLocal            2
+0010    | Return
+0011    1 Nil
+0012    | Return
+
+== middle ==
+0000    4 Closure             0 <fn inner>
+0003    |                     upvalue 0
+0005    5 GetLocal            1
+0007    | Return
+0008    3 Nil
+0009    | Return
+
+== inner ==
+0000    4 GetUpvalue          0
+0002    | Return
+0003    | Nil
+0004    | Return
+"#;
+        assert_eq!(disassemble_function(&script), expected);
+    }
+}
@@ -0,0 +1,230 @@
+use std::fmt::Write;
+
+use crate::{json, token::Span};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+}
+
+impl Severity {
+    fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// A message attached to a range of source text.
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// A problem found in a Lox program, rendered rustc-style with the offending
+/// source line and a caret underline. A primary span on line 0 means the
+/// problem has no position in the source.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub primary: Label,
+    pub secondary: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static str, message: &str, span: Span) -> Self {
+        Self {
+            severity: Severity::Error,
+            code,
+            message: message.to_string(),
+            primary: Label {
+                span,
+                message: String::new(),
+            },
+            secondary: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    /// Sets the text printed next to the primary underline.
+    pub fn with_label(mut self, message: &str) -> Self {
+        self.primary.message = message.to_string();
+        self
+    }
+
+    pub fn with_secondary(mut self, span: Span, message: &str) -> Self {
+        self.secondary.push(Label {
+            span,
+            message: message.to_string(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Self {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    /// Renders the diagnostic against `source`, the full text of the file
+    /// called `name`.
+    pub fn render(&self, name: &str, source: &str, color: bool) -> String {
+        let style = Style { color };
+        let lines: Vec<&str> = source.lines().collect();
+
+        let mut labels: Vec<(&Label, bool)> = vec![(&self.primary, true)];
+        labels.extend(self.secondary.iter().map(|label| (label, false)));
+        labels.sort_by_key(|(label, _)| (label.span.line, label.span.column));
+
+        let max_line = labels
+            .iter()
+            .map(|(label, _)| label.span.line)
+            .max()
+            .unwrap_or(1);
+        let pad = " ".repeat(max_line.to_string().len());
+        let gutter = style.paint(Style::BLUE, "|");
+
+        let mut out = String::new();
+        let severity = style.paint(
+            Style::RED,
+            &format!("{}[{}]", self.severity.name(), self.code),
+        );
+        let _ = writeln!(
+            out,
+            "{severity}{}",
+            style.paint(Style::BOLD, &format!(": {}", self.message))
+        );
+        // Errors in compiled bytecode have no source to show.
+        if self.primary.span.line == 0 || source.is_empty() {
+            let location = match self.primary.span.line {
+                0 => String::new(),
+                line => format!(":{line}:{}", self.primary.span.column),
+            };
+            let _ = writeln!(out, "{pad}{} {name}{location}", style.paint(Style::BLUE, "-->"));
+            labels.clear();
+        } else {
+            let _ = writeln!(
+                out,
+                "{pad}{} {name}:{}:{}",
+                style.paint(Style::BLUE, "-->"),
+                self.primary.span.line,
+                self.primary.span.column
+            );
+            let _ = writeln!(out, "{pad} {gutter}");
+        }
+
+        let mut previous_line = None;
+        for (label, is_primary) in labels {
+            let line = label.span.line;
+            let text = lines.get(line.wrapping_sub(1)).copied().unwrap_or("");
+
+            if previous_line != Some(line) {
+                if previous_line.is_some_and(|previous| line > previous + 1) {
+                    let _ = writeln!(out, "{}", style.paint(Style::BLUE, "..."));
+                }
+                let number =
+                    style.paint(Style::BLUE, &format!("{line:>width$}", width = pad.len()));
+                let _ = writeln!(out, "{number} {gutter} {text}");
+                previous_line = Some(line);
+            }
+
+            // Reuse the line's own whitespace so tabs keep the underline aligned.
+            let indent: String = text
+                .chars()
+                .take(label.span.column.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let width = underline_width(source, label.span);
+            let (marker, colour) = if is_primary {
+                ('^', Style::RED)
+            } else {
+                ('-', Style::BLUE)
+            };
+            let mut underline = marker.to_string().repeat(width);
+            if !label.message.is_empty() {
+                underline.push(' ');
+                underline.push_str(&label.message);
+            }
+            let _ = writeln!(
+                out,
+                "{pad} {gutter} {indent}{}",
+                style.paint(colour, &underline)
+            );
+        }
+
+        for note in &self.notes {
+            let _ = writeln!(
+                out,
+                "{pad} {} {}: {note}",
+                style.paint(Style::BLUE, "="),
+                style.paint(Style::BOLD, "note")
+            );
+        }
+
+        out
+    }
+
+    /// Renders the diagnostic as a single line of JSON. Lines and columns are
+    /// 1-based, and the end position points just past the primary span.
+    pub fn to_json(&self, name: &str, source: &str) -> String {
+        let span = self.primary.span;
+        let (end_line, end_column) = if source.is_empty() {
+            (span.line, span.column)
+        } else {
+            position(source, span.end)
+        };
+        let notes: Vec<String> = self.notes.iter().map(|note| json::string(note)).collect();
+
+        format!(
+            "{{\"severity\":{},\"code\":{},\"message\":{},\"file\":{},\"line\":{},\"column\":{},\"end_line\":{end_line},\"end_column\":{end_column},\"label\":{},\"notes\":[{}]}}",
+            json::string(self.severity.name()),
+            json::string(self.code),
+            json::string(&self.message),
+            json::string(name),
+            span.line,
+            span.column,
+            json::string(&self.primary.message),
+            notes.join(",")
+        )
+    }
+}
+
+/// The 1-based line and column of the byte `offset` in `source`.
+fn position(source: &str, offset: usize) -> (usize, usize) {
+    let before = source.get(..offset).unwrap_or(source);
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
+
+/// The number of characters to underline for `span`, stopping at the end of
+/// its first line. Empty spans, like the one at the end of the file, still get
+/// a single caret.
+fn underline_width(source: &str, span: Span) -> usize {
+    let text = source.get(span.start..span.end).unwrap_or("");
+    let first_line = text.split('\n').next().unwrap_or("");
+    first_line.trim_end_matches('\r').chars().count().max(1)
+}
+
+struct Style {
+    color: bool,
+}
+
+impl Style {
+    const BOLD: &'static str = "\x1b[1m";
+    const RED: &'static str = "\x1b[1;31m";
+    const BLUE: &'static str = "\x1b[1;34m";
+    const RESET: &'static str = "\x1b[0m";
+
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.color {
+            format!("{code}{text}{}", Self::RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
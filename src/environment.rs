@@ -0,0 +1,83 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{interpreter::InterpreterError, object::Object, symbol::Symbol, token::Token};
+
+pub struct Environment {
+    enclosing: Option<Rc<RefCell<Environment>>>,
+    values: HashMap<Symbol, Object>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self {
+            enclosing: None,
+            values: HashMap::new(),
+        }
+    }
+
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            enclosing: Some(enclosing),
+            values: HashMap::new(),
+        }
+    }
+
+    pub fn define(&mut self, name: Symbol, value: Object) {
+        self.values.insert(name, value);
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Object, InterpreterError> {
+        if let Some(value) = self.values.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get(name),
+            None => Err(InterpreterError::UndefinedVariable { name: name.clone() }),
+        }
+    }
+
+    pub fn assign(&mut self, name: &Token, value: Object) -> Result<(), InterpreterError> {
+        if let Some(slot) = self.values.get_mut(&name.lexeme) {
+            *slot = value;
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(InterpreterError::UndefinedVariable { name: name.clone() }),
+        }
+    }
+
+    /// Looks `name` up in the environment `distance` hops up the chain.
+    pub fn get_at(&self, distance: usize, name: Symbol) -> Option<Object> {
+        if distance == 0 {
+            return self.values.get(&name).cloned();
+        }
+
+        self.enclosing
+            .as_ref()
+            .expect("Compiler error: resolved scope depth is deeper than the environment chain")
+            .borrow()
+            .get_at(distance - 1, name)
+    }
+
+    /// Assigns `name` in the environment `distance` hops up the chain.
+    pub fn assign_at(&mut self, distance: usize, name: &Token, value: Object) -> Result<(), InterpreterError> {
+        if distance == 0 {
+            return match self.values.get_mut(&name.lexeme) {
+                Some(slot) => {
+                    *slot = value;
+                    Ok(())
+                }
+                None => Err(InterpreterError::UndefinedVariable { name: name.clone() }),
+            };
+        }
+
+        self.enclosing
+            .as_ref()
+            .expect("Compiler error: resolved scope depth is deeper than the environment chain")
+            .borrow_mut()
+            .assign_at(distance - 1, name, value)
+    }
+}
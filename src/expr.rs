@@ -1,7 +1,9 @@
-use core::fmt;
-use std::rc::Rc;
+use std::{cell::Cell, rc::Rc};
 
-use crate::{literal::Literal, token::Token};
+use crate::{
+    literal::Literal,
+    token::{Span, Token},
+};
 
 
 pub trait ExpressionVisitor<T> {
@@ -9,14 +11,31 @@ pub trait ExpressionVisitor<T> {
     fn visit_unary(&self, operand: &Token, expr: &Expr) -> T;
     fn visit_literal(&self, literal: &Literal) -> T;
     fn visit_grouping(&self, expr: &Expr) -> T;
+    fn visit_variable(&self, name: &Token, depth: &ScopeDepth) -> T;
+    fn visit_assign(&self, name: &Token, value: &Expr, depth: &ScopeDepth) -> T;
+    fn visit_logical(&self, left: &Expr, operator: &Token, right: &Expr) -> T;
+    fn visit_conditional(&self, condition: &Expr, then_branch: &Expr, else_branch: &Expr) -> T;
+    fn visit_call(&self, callee: &Expr, paren: &Token, arguments: &[Expr]) -> T;
+    fn visit_get(&self, object: &Expr, name: &Token) -> T;
+    fn visit_set(&self, object: &Expr, name: &Token, value: &Expr) -> T;
+    fn visit_this(&self, keyword: &Token, depth: &ScopeDepth) -> T;
+    fn visit_super(&self, keyword: &Token, method: &Token, depth: &ScopeDepth) -> T;
 }
 
 pub enum Expr {
     Binary(BinaryData),
     Unary(UnaryData),
-    Literal(Literal),
-    Grouping(GroupingData)
-
+    Literal(LiteralData),
+    Grouping(GroupingData),
+    Variable(VariableData),
+    Assign(AssignData),
+    Logical(LogicalData),
+    Conditional(ConditionalData),
+    Call(CallData),
+    Get(GetData),
+    Set(SetData),
+    This(ThisData),
+    Super(SuperData),
 }
 
 impl Expr {
@@ -24,8 +43,38 @@ impl Expr {
         match self {
             Expr::Binary(data) => visitor.visit_binary(&data.left, &data.operator, &data.right),
             Expr::Unary(data) => visitor.visit_unary(&data.operator, &data.right),
-            Expr::Literal(data) => visitor.visit_literal(data),
+            Expr::Literal(data) => visitor.visit_literal(&data.value),
             Expr::Grouping(data) => visitor.visit_grouping(&data.expression),
+            Expr::Variable(data) => visitor.visit_variable(&data.name, &data.depth),
+            Expr::Assign(data) => visitor.visit_assign(&data.name, &data.value, &data.depth),
+            Expr::Logical(data) => visitor.visit_logical(&data.left, &data.operator, &data.right),
+            Expr::Conditional(data) => {
+                visitor.visit_conditional(&data.condition, &data.then_branch, &data.else_branch)
+            }
+            Expr::Call(data) => visitor.visit_call(&data.callee, &data.paren, &data.arguments),
+            Expr::Get(data) => visitor.visit_get(&data.object, &data.name),
+            Expr::Set(data) => visitor.visit_set(&data.object, &data.name, &data.value),
+            Expr::This(data) => visitor.visit_this(&data.keyword, &data.depth),
+            Expr::Super(data) => visitor.visit_super(&data.keyword, &data.method, &data.depth),
+        }
+    }
+
+    /// The source range the expression was parsed from.
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Binary(data) => data.left.span().to(data.right.span()),
+            Expr::Unary(data) => data.operator.span.to(data.right.span()),
+            Expr::Literal(data) => data.span,
+            Expr::Grouping(data) => data.span,
+            Expr::Variable(data) => data.name.span,
+            Expr::Assign(data) => data.name.span.to(data.value.span()),
+            Expr::Logical(data) => data.left.span().to(data.right.span()),
+            Expr::Conditional(data) => data.condition.span().to(data.else_branch.span()),
+            Expr::Call(data) => data.callee.span().to(data.paren.span),
+            Expr::Get(data) => data.object.span().to(data.name.span),
+            Expr::Set(data) => data.object.span().to(data.value.span()),
+            Expr::This(data) => data.keyword.span,
+            Expr::Super(data) => data.keyword.span.to(data.method.span),
         }
     }
 }
@@ -42,9 +91,68 @@ pub struct UnaryData{
     pub operator: Token,
 }
 
+pub struct LiteralData{
+    pub value: Literal,
+    pub span: Span,
+}
+
 pub struct GroupingData{
     pub expression: Rc<Expr>,
+    /// Covers the parentheses as well as the inner expression.
+    pub span: Span,
+}
+
+/// The number of scopes between a variable reference and the scope that
+/// declares it, filled in by the resolver. `None` means the variable is global.
+pub type ScopeDepth = Cell<Option<usize>>;
+
+pub struct VariableData{
+    pub name: Token,
+    pub depth: ScopeDepth,
+}
+
+pub struct AssignData{
+    pub name: Token,
+    pub value: Rc<Expr>,
+    pub depth: ScopeDepth,
+}
+
+pub struct LogicalData{
+    pub left: Rc<Expr>,
+    pub right: Rc<Expr>,
+    pub operator: Token,
 }
 
+pub struct ConditionalData{
+    pub condition: Rc<Expr>,
+    pub then_branch: Rc<Expr>,
+    pub else_branch: Rc<Expr>,
+}
 
+pub struct CallData{
+    pub callee: Rc<Expr>,
+    pub paren: Token,
+    pub arguments: Vec<Expr>,
+}
 
+pub struct GetData{
+    pub object: Rc<Expr>,
+    pub name: Token,
+}
+
+pub struct SetData{
+    pub object: Rc<Expr>,
+    pub name: Token,
+    pub value: Rc<Expr>,
+}
+
+pub struct ThisData{
+    pub keyword: Token,
+    pub depth: ScopeDepth,
+}
+
+pub struct SuperData{
+    pub keyword: Token,
+    pub method: Token,
+    pub depth: ScopeDepth,
+}
@@ -1,36 +1,591 @@
-use crate::{expr::{Expr, ExpressionVisitor}, object::Object, token::TokenType};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-pub enum InterpreterError{
+use thiserror::Error;
 
+use crate::{
+    diagnostic::Diagnostic,
+    callable::{LoxFunction, NativeFunction},
+    class::{LoxClass, LoxInstance},
+    environment::Environment,
+    expr::{Expr, ExpressionVisitor, ScopeDepth},
+    literal::Literal,
+    object::Object,
+    expr::VariableData,
+    stmt::{FunctionData, StatementVisitor, Stmt},
+    symbol::Symbol,
+    token::{Span, Token, TokenType},
+    vm::FRAMES_MAX,
+};
+
+#[derive(Error, Debug)]
+pub enum InterpreterError {
+    #[error("Operand must be a number.")]
+    OperandMustBeNumber { operator: Token },
+
+    #[error("Operands must be numbers.")]
+    OperandsMustBeNumbers { operator: Token },
+
+    #[error("Operands must be two numbers or two strings.")]
+    OperandsMustBeNumbersOrStrings { operator: Token },
+
+    #[error("Division by zero.")]
+    DivisionByZero { operator: Token },
+
+    #[error("Undefined variable '{}'.", name.lexeme)]
+    UndefinedVariable { name: Token },
+
+    #[error("Can only call functions and classes.")]
+    NotCallable { paren: Token },
+
+    #[error("Expected {expected} arguments but got {got}.")]
+    ArityMismatch {
+        paren: Token,
+        expected: usize,
+        got: usize,
+    },
+
+    #[error("Undefined property '{}'.", name.lexeme)]
+    UndefinedProperty { name: Token },
+
+    #[error("Only instances have properties.")]
+    OnlyInstancesHaveProperties { name: Token },
+
+    #[error("Only instances have fields.")]
+    OnlyInstancesHaveFields { name: Token },
+
+    #[error("Superclass must be a class.")]
+    SuperclassMustBeClass { name: Token },
+
+    #[error("Stack overflow.")]
+    StackOverflow { paren: Token },
+
+    /// Only raised by the VM, for bytecode that passed verification but
+    /// hands an instruction the wrong kind of value.
+    #[error("Malformed bytecode: expected {expected}.")]
+    MalformedBytecode { at: Token, expected: &'static str },
 }
 
-pub struct Interpreter;
+impl InterpreterError {
+    pub fn span(&self) -> Span {
+        self.token().span
+    }
+
+    /// A stable identifier for the kind of error, used in JSON diagnostics.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::OperandMustBeNumber { .. } => "E0300",
+            Self::OperandsMustBeNumbers { .. } => "E0301",
+            Self::OperandsMustBeNumbersOrStrings { .. } => "E0302",
+            Self::DivisionByZero { .. } => "E0303",
+            Self::UndefinedVariable { .. } => "E0304",
+            Self::NotCallable { .. } => "E0305",
+            Self::ArityMismatch { .. } => "E0306",
+            Self::UndefinedProperty { .. } => "E0307",
+            Self::OnlyInstancesHaveProperties { .. } => "E0308",
+            Self::OnlyInstancesHaveFields { .. } => "E0309",
+            Self::SuperclassMustBeClass { .. } => "E0310",
+            Self::StackOverflow { .. } => "E0311",
+            Self::MalformedBytecode { .. } => "E0312",
+        }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::error(self.code(), &self.to_string(), self.span())
+    }
+
+    /// The token the error should be reported at.
+    pub fn token(&self) -> &Token {
+        match self {
+            Self::OperandMustBeNumber { operator }
+            | Self::OperandsMustBeNumbers { operator }
+            | Self::OperandsMustBeNumbersOrStrings { operator }
+            | Self::DivisionByZero { operator } => operator,
+            Self::UndefinedVariable { name }
+            | Self::UndefinedProperty { name }
+            | Self::OnlyInstancesHaveProperties { name }
+            | Self::OnlyInstancesHaveFields { name }
+            | Self::SuperclassMustBeClass { name } => name,
+            Self::NotCallable { paren }
+            | Self::ArityMismatch { paren, .. }
+            | Self::StackOverflow { paren } => paren,
+            Self::MalformedBytecode { at, .. } => at,
+        }
+    }
+}
+
+/// Ways statement execution can stop early and unwind to an enclosing
+/// statement.
+pub enum Unwind {
+    Error(InterpreterError),
+    Break,
+    Continue,
+    Return(Object),
+}
+
+impl From<InterpreterError> for Unwind {
+    fn from(error: InterpreterError) -> Self {
+        Unwind::Error(error)
+    }
+}
+
+pub struct Interpreter {
+    globals: Rc<RefCell<Environment>>,
+    environment: RefCell<Rc<RefCell<Environment>>>,
+    /// How many Lox functions are running.
+    call_depth: Cell<usize>,
+}
 
 impl Interpreter {
+    pub fn new() -> Self {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals.borrow_mut().define(
+            Symbol::intern("clock"),
+            Object::NativeFunction(Rc::new(NativeFunction {
+                arity: 0,
+                function: |_| {
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .expect("System clock is set before the Unix epoch");
+                    now.as_secs_f64().into()
+                },
+            })),
+        );
+
+        Self {
+            environment: RefCell::new(globals.clone()),
+            globals,
+            call_depth: Cell::new(0),
+        }
+    }
+
+    pub fn interpret(&self, statements: &[Stmt]) -> Result<(), InterpreterError> {
+        for statement in statements {
+            match self.execute(statement) {
+                Ok(()) => (),
+                Err(Unwind::Error(e)) => return Err(e),
+                Err(Unwind::Break | Unwind::Continue | Unwind::Return(_)) => {
+                    unreachable!("Compiler error: control flow escaped to the top level")
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn execute(&self, stmt: &Stmt) -> Result<(), Unwind> {
+        stmt.accept(self)
+    }
+
+    pub fn execute_block(
+        &self,
+        statements: &[Stmt],
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<(), Unwind> {
+        let previous = self.environment.replace(environment);
+        let result = statements.iter().try_for_each(|s| self.execute(s));
+        self.environment.replace(previous);
+        result
+    }
+
     fn evaluate(&self, expr: &Expr) -> Result<Object, InterpreterError> {
         expr.accept(self)
     }
+
+    fn look_up_variable(&self, name: &Token, depth: &ScopeDepth) -> Result<Object, InterpreterError> {
+        match depth.get() {
+            Some(distance) => self
+                .environment
+                .borrow()
+                .borrow()
+                .get_at(distance, name.lexeme)
+                .ok_or_else(|| InterpreterError::UndefinedVariable { name: name.clone() }),
+            None => self.globals.borrow().get(name),
+        }
+    }
+
+    fn number_operand(&self, operator: &Token, operand: &Object) -> Result<f64, InterpreterError> {
+        match operand {
+            Object::Literal(Literal::Number(n)) => Ok(*n),
+            _ => Err(InterpreterError::OperandMustBeNumber {
+                operator: operator.clone(),
+            }),
+        }
+    }
+
+    fn number_operands(
+        &self,
+        operator: &Token,
+        left: &Object,
+        right: &Object,
+    ) -> Result<(f64, f64), InterpreterError> {
+        match (left, right) {
+            (Object::Literal(Literal::Number(l)), Object::Literal(Literal::Number(r))) => {
+                Ok((*l, *r))
+            }
+            _ => Err(InterpreterError::OperandsMustBeNumbers {
+                operator: operator.clone(),
+            }),
+        }
+    }
 }
 
 impl ExpressionVisitor<Result<Object, InterpreterError>> for Interpreter {
-    fn visit_binary(&self, left: &crate::expr::Expr, operand: &crate::token::Token, right: &crate::expr::Expr) -> Result<Object, InterpreterError> {
-        todo!()
+    fn visit_binary(&self, left: &Expr, operand: &Token, right: &Expr) -> Result<Object, InterpreterError> {
+        let left = self.evaluate(left)?;
+        let right = self.evaluate(right)?;
+
+        use TokenType::*;
+        match operand.ttype {
+            Plus => match (&left, &right) {
+                (Object::Literal(Literal::Number(l)), Object::Literal(Literal::Number(r))) => {
+                    Ok((l + r).into())
+                }
+                _ => match (left.as_str(), right.as_str()) {
+                    (Some(l), Some(r)) => Ok(format!("{l}{r}").into()),
+                    _ => Err(InterpreterError::OperandsMustBeNumbersOrStrings {
+                        operator: operand.clone(),
+                    }),
+                },
+            },
+            Minus => {
+                let (l, r) = self.number_operands(operand, &left, &right)?;
+                Ok((l - r).into())
+            }
+            Star => {
+                let (l, r) = self.number_operands(operand, &left, &right)?;
+                Ok((l * r).into())
+            }
+            Slash => {
+                let (l, r) = self.number_operands(operand, &left, &right)?;
+                if r == 0.0 {
+                    return Err(InterpreterError::DivisionByZero {
+                        operator: operand.clone(),
+                    });
+                }
+                Ok((l / r).into())
+            }
+            Greater => {
+                let (l, r) = self.number_operands(operand, &left, &right)?;
+                Ok((l > r).into())
+            }
+            GreaterEqual => {
+                let (l, r) = self.number_operands(operand, &left, &right)?;
+                Ok((l >= r).into())
+            }
+            Less => {
+                let (l, r) = self.number_operands(operand, &left, &right)?;
+                Ok((l < r).into())
+            }
+            LessEqual => {
+                let (l, r) = self.number_operands(operand, &left, &right)?;
+                Ok((l <= r).into())
+            }
+            EqualEqual => Ok((left == right).into()),
+            BangEqual => Ok((left != right).into()),
+            Comma => Ok(right),
+            _ => unreachable!("Compiler error: invalid binary operator {:?}", operand.ttype),
+        }
     }
 
-    fn visit_unary(&self, operand: &crate::token::Token, expr: &crate::expr::Expr) -> Result<Object, InterpreterError> {
-        todo!()
-        // let right = self.evaluate(expr)?;
-        // use TokenType::*;
-        // match operand.ttype {
-        //     Minus => 
-        // }
+    fn visit_unary(&self, operand: &Token, expr: &Expr) -> Result<Object, InterpreterError> {
+        let right = self.evaluate(expr)?;
+
+        use TokenType::*;
+        match operand.ttype {
+            Minus => Ok((-self.number_operand(operand, &right)?).into()),
+            Bang => Ok((!right.is_truthy()).into()),
+            _ => unreachable!("Compiler error: invalid unary operator {:?}", operand.ttype),
+        }
     }
 
-    fn visit_literal(&self, literal: &crate::literal::Literal) -> Result<Object, InterpreterError> {
+    fn visit_literal(&self, literal: &Literal) -> Result<Object, InterpreterError> {
         Ok(Object::Literal(literal.clone()))
     }
 
-    fn visit_grouping(&self, expr: &crate::expr::Expr) -> Result<Object, InterpreterError> {
+    fn visit_grouping(&self, expr: &Expr) -> Result<Object, InterpreterError> {
         self.evaluate(expr)
     }
-}
\ No newline at end of file
+
+    fn visit_variable(&self, name: &Token, depth: &ScopeDepth) -> Result<Object, InterpreterError> {
+        self.look_up_variable(name, depth)
+    }
+
+    fn visit_assign(&self, name: &Token, value: &Expr, depth: &ScopeDepth) -> Result<Object, InterpreterError> {
+        let value = self.evaluate(value)?;
+        match depth.get() {
+            Some(distance) => {
+                self.environment
+                    .borrow()
+                    .borrow_mut()
+                    .assign_at(distance, name, value.clone())?;
+            }
+            None => self.globals.borrow_mut().assign(name, value.clone())?,
+        }
+        Ok(value)
+    }
+
+    fn visit_logical(&self, left: &Expr, operator: &Token, right: &Expr) -> Result<Object, InterpreterError> {
+        let left = self.evaluate(left)?;
+
+        let short_circuits = match operator.ttype {
+            TokenType::Or => left.is_truthy(),
+            TokenType::And => !left.is_truthy(),
+            _ => unreachable!("Compiler error: invalid logical operator {:?}", operator.ttype),
+        };
+
+        if short_circuits {
+            Ok(left)
+        } else {
+            self.evaluate(right)
+        }
+    }
+
+    fn visit_conditional(
+        &self,
+        condition: &Expr,
+        then_branch: &Expr,
+        else_branch: &Expr,
+    ) -> Result<Object, InterpreterError> {
+        if self.evaluate(condition)?.is_truthy() {
+            self.evaluate(then_branch)
+        } else {
+            self.evaluate(else_branch)
+        }
+    }
+
+    fn visit_call(&self, callee: &Expr, paren: &Token, arguments: &[Expr]) -> Result<Object, InterpreterError> {
+        let callee = self.evaluate(callee)?;
+        let arguments = arguments
+            .iter()
+            .map(|argument| self.evaluate(argument))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let Some(function) = callee.as_callable() else {
+            return Err(InterpreterError::NotCallable {
+                paren: paren.clone(),
+            });
+        };
+
+        if arguments.len() != function.arity() {
+            return Err(InterpreterError::ArityMismatch {
+                paren: paren.clone(),
+                expected: function.arity(),
+                got: arguments.len(),
+            });
+        }
+
+        // Natives and classes without an initializer don't run Lox code, so
+        // they can't recurse.
+        let runs_lox_code = match &callee {
+            Object::Function(_) => true,
+            Object::Class(class) => class.find_method(Symbol::intern("init")).is_some(),
+            _ => false,
+        };
+        // Overflow at the same depth as the VM.
+        if runs_lox_code && self.call_depth.get() + 1 >= FRAMES_MAX {
+            return Err(InterpreterError::StackOverflow {
+                paren: paren.clone(),
+            });
+        }
+
+        self.call_depth.set(self.call_depth.get() + 1);
+        let result = function.call(self, arguments);
+        self.call_depth.set(self.call_depth.get() - 1);
+        result
+    }
+
+    fn visit_get(&self, object: &Expr, name: &Token) -> Result<Object, InterpreterError> {
+        match self.evaluate(object)? {
+            Object::Instance(instance) => LoxInstance::get(&instance, name),
+            _ => Err(InterpreterError::OnlyInstancesHaveProperties { name: name.clone() }),
+        }
+    }
+
+    fn visit_set(&self, object: &Expr, name: &Token, value: &Expr) -> Result<Object, InterpreterError> {
+        let Object::Instance(instance) = self.evaluate(object)? else {
+            return Err(InterpreterError::OnlyInstancesHaveFields { name: name.clone() });
+        };
+
+        let value = self.evaluate(value)?;
+        instance.borrow_mut().set(name, value.clone());
+        Ok(value)
+    }
+
+    fn visit_this(&self, keyword: &Token, depth: &ScopeDepth) -> Result<Object, InterpreterError> {
+        self.look_up_variable(keyword, depth)
+    }
+
+    fn visit_super(&self, keyword: &Token, method: &Token, depth: &ScopeDepth) -> Result<Object, InterpreterError> {
+        let distance = depth
+            .get()
+            .expect("Compiler error: 'super' was not resolved to a scope");
+        let environment = self.environment.borrow();
+
+        let Some(Object::Class(superclass)) = environment.borrow().get_at(distance, Symbol::intern("super")) else {
+            return Err(InterpreterError::UndefinedVariable {
+                name: keyword.clone(),
+            });
+        };
+        // `this` always lives in the scope just inside the one binding `super`.
+        let object = environment
+            .borrow()
+            .get_at(distance - 1, Symbol::intern("this"))
+            .expect("Compiler error: 'super' used outside of a method");
+
+        match superclass.find_method(method.lexeme) {
+            Some(function) => Ok(Object::Function(Rc::new(function.bind(object)))),
+            None => Err(InterpreterError::UndefinedProperty {
+                name: method.clone(),
+            }),
+        }
+    }
+}
+
+impl StatementVisitor<Result<(), Unwind>> for Interpreter {
+    fn visit_expression(&self, expr: &Expr) -> Result<(), Unwind> {
+        self.evaluate(expr)?;
+        Ok(())
+    }
+
+    fn visit_print(&self, expr: &Expr) -> Result<(), Unwind> {
+        let value = self.evaluate(expr)?;
+        println!("{value}");
+        Ok(())
+    }
+
+    fn visit_var(&self, name: &Token, initializer: Option<&Expr>) -> Result<(), Unwind> {
+        let value = match initializer {
+            Some(expr) => self.evaluate(expr)?,
+            None => Object::Literal(Literal::Nil),
+        };
+
+        self.environment
+            .borrow()
+            .borrow_mut()
+            .define(name.lexeme, value);
+        Ok(())
+    }
+
+    fn visit_block(&self, statements: &[Stmt]) -> Result<(), Unwind> {
+        let environment = Environment::with_enclosing(self.environment.borrow().clone());
+        self.execute_block(statements, Rc::new(RefCell::new(environment)))
+    }
+
+    fn visit_if(
+        &self,
+        condition: &Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> Result<(), Unwind> {
+        if self.evaluate(condition)?.is_truthy() {
+            self.execute(then_branch)
+        } else if let Some(else_branch) = else_branch {
+            self.execute(else_branch)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn visit_while(
+        &self,
+        condition: &Expr,
+        body: &Stmt,
+        increment: Option<&Expr>,
+    ) -> Result<(), Unwind> {
+        while self.evaluate(condition)?.is_truthy() {
+            match self.execute(body) {
+                Ok(()) | Err(Unwind::Continue) => (),
+                Err(Unwind::Break) => break,
+                Err(e) => return Err(e),
+            }
+
+            if let Some(increment) = increment {
+                self.evaluate(increment)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_break(&self, _keyword: &Token) -> Result<(), Unwind> {
+        Err(Unwind::Break)
+    }
+
+    fn visit_continue(&self, _keyword: &Token) -> Result<(), Unwind> {
+        Err(Unwind::Continue)
+    }
+
+    fn visit_function(&self, declaration: &Rc<FunctionData>) -> Result<(), Unwind> {
+        let function = LoxFunction::new(
+            declaration.clone(),
+            self.environment.borrow().clone(),
+            false,
+        );
+        self.environment
+            .borrow()
+            .borrow_mut()
+            .define(declaration.name.lexeme, Object::Function(Rc::new(function)));
+        Ok(())
+    }
+
+    fn visit_return(&self, _keyword: &Token, value: Option<&Expr>) -> Result<(), Unwind> {
+        let value = match value {
+            Some(expr) => self.evaluate(expr)?,
+            None => Object::Literal(Literal::Nil),
+        };
+        Err(Unwind::Return(value))
+    }
+
+    fn visit_class(
+        &self,
+        name: &Token,
+        superclass: Option<&VariableData>,
+        methods: &[Rc<FunctionData>],
+    ) -> Result<(), Unwind> {
+        let superclass = match superclass {
+            Some(variable) => match self.look_up_variable(&variable.name, &variable.depth)? {
+                Object::Class(class) => Some(class),
+                _ => {
+                    return Err(InterpreterError::SuperclassMustBeClass {
+                        name: variable.name.clone(),
+                    }
+                    .into())
+                }
+            },
+            None => None,
+        };
+
+        let enclosing = self.environment.borrow().clone();
+        enclosing
+            .borrow_mut()
+            .define(name.lexeme, Object::Literal(Literal::Nil));
+
+        // Methods of a subclass close over an extra scope that binds `super`.
+        let environment = match &superclass {
+            Some(superclass) => {
+                let mut environment = Environment::with_enclosing(enclosing.clone());
+                environment.define(Symbol::intern("super"), Object::Class(superclass.clone()));
+                Rc::new(RefCell::new(environment))
+            }
+            None => enclosing.clone(),
+        };
+
+        let methods = methods
+            .iter()
+            .map(|method| {
+                let is_initializer = method.name.lexeme.as_str() == "init";
+                let function = LoxFunction::new(method.clone(), environment.clone(), is_initializer);
+                (method.name.lexeme, Rc::new(function))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let class = LoxClass::new(&name.lexeme, superclass, methods);
+        enclosing
+            .borrow_mut()
+            .assign(name, Object::Class(Rc::new(class)))?;
+        Ok(())
+    }
+}
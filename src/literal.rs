@@ -1,9 +1,11 @@
 use std::fmt;
 
-#[derive(Debug, Clone)]
+use crate::symbol::Symbol;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Literal{
     Number(f64),
-    String(String),
+    String(Symbol),
     Bool(bool),
     Nil,
 }
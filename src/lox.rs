@@ -1,42 +1,186 @@
-use std::{fs, io, process::exit};
+use std::{
+    fs,
+    io::{self, Write},
+    process::exit,
+    rc::Rc,
+};
 
 use crate::{
+    ast_dot::DotPrinter,
+    ast_json::JsonPrinter,
     ast_printer::AstPrinter,
+    ast_sexpr::{self, SexprError, SexprPrinter},
+    chunk::Function,
+    compiler::{Compiler, CompilerError},
+    debug,
+    diagnostic::Diagnostic,
+    interpreter::{Interpreter, InterpreterError},
+    loxc::{self, LoxcError},
     parser::{Parser, ParserError},
+    resolver::{Resolver, ResolverError},
     scanner::{Scanner, ScannerError},
-    token::{Token, TokenType},
+    stmt::Stmt,
+    token::Token,
+    value::Heap,
+    vm::Vm,
 };
 
+/// What to do with a script.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Command {
+    /// Interpret the script.
+    Run,
+    /// Print the tokens the scanner produces.
+    Tokens,
+    /// Print the parsed syntax tree.
+    Ast,
+    /// Scan, parse and resolve the script without running it.
+    Check,
+    /// Print the bytecode the script compiles to.
+    Disassemble,
+    /// Write the script's bytecode to a `.loxc` file.
+    Compile,
+}
+
+/// How `lox ast` writes the syntax tree.
+#[derive(Clone, Copy, PartialEq)]
+pub enum AstFormat {
+    Lisp,
+    Json,
+    Sexpr,
+    Dot,
+}
+
+/// Which engine runs programs.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Backend {
+    /// Walk the syntax tree directly.
+    Tree,
+    /// Compile to bytecode and run it on the virtual machine.
+    Vm,
+}
+
 enum LoxError {
+    Sexpr(SexprError),
     Scanner(Vec<ScannerError>),
-    Parser(ParserError),
+    Parser(Vec<ParserError>),
+    Resolver(Vec<ResolverError>),
+    Compiler(Vec<CompilerError>),
+    Loxc(LoxcError),
+    Interpreter(InterpreterError),
+}
+
+/// How diagnostics are written to stderr.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ErrorFormat {
+    /// Caret-underlined source excerpts meant for people.
+    Human,
+    /// One JSON object per line, meant for editors and other tools.
+    Json,
+}
+
+/// Settings chosen on the command line.
+pub struct Options {
+    pub color: bool,
+    pub error_format: ErrorFormat,
+    pub ast_format: AstFormat,
+    /// Read the script as a single expression in the S-expression format
+    /// instead of as Lox source. Only used by `lox ast`.
+    pub from_sexpr: bool,
+    pub backend: Backend,
+    /// Print each bytecode instruction and the stack as the VM runs.
+    pub trace_execution: bool,
+    /// Where `lox compile` writes the bytecode.
+    pub output: Option<String>,
+    /// Run the VM's garbage collector after every allocation.
+    pub gc_stress: bool,
+    /// How far the VM's heap grows past the live objects before the next
+    /// collection.
+    pub gc_growth_factor: f64,
 }
 
 pub struct Lox {
+    interpreter: Interpreter,
+    vm: Vm,
+    options: Options,
     had_error: bool,
+    had_runtime_error: bool,
 }
 
 impl Lox {
-    pub fn new() -> Self {
-        Self { had_error: false }
+    pub fn new(options: Options) -> Self {
+        Self {
+            interpreter: Interpreter::new(),
+            vm: Vm::new(
+                Heap::new(options.gc_growth_factor, options.gc_stress),
+                options.trace_execution,
+            ),
+            options,
+            had_error: false,
+            had_runtime_error: false,
+        }
     }
 
-    pub fn run_file(&self, path: &str) {
-        let content = fs::read_to_string(path).expect("Unable to read file");
-        self.run(&content);
+    /// Carries out `command` on `source`, the contents of the script called
+    /// `name`, and exits with an error code if anything went wrong.
+    pub fn run_script(&mut self, command: Command, name: &str, source: &str) {
+        let result = match command {
+            Command::Run => self.run(source),
+            Command::Tokens => self.tokens(source),
+            Command::Ast => self.ast(source),
+            Command::Check => self.check(source).map(|_| ()),
+            Command::Disassemble => self.disassemble(source),
+            Command::Compile => self.write_bytecode(name, source),
+        };
+        if let Err(e) = result {
+            self.report_error(name, source, e);
+        }
+        self.exit_on_error();
+    }
+
+    /// Runs or disassembles `bytes`, the contents of the compiled file called
+    /// `name`, on the virtual machine and exits with an error code if anything
+    /// went wrong. Other commands need source code.
+    pub fn run_bytecode(&mut self, command: Command, name: &str, bytes: &[u8]) {
+        match loxc::read(bytes) {
+            Ok((source_name, script)) => {
+                let result = match command {
+                    Command::Disassemble => {
+                        print!("{}", debug::disassemble_function(&script));
+                        Ok(())
+                    }
+                    _ => self.vm.interpret(script).map_err(LoxError::Interpreter),
+                };
+                // Runtime errors point into the original source, which the
+                // compiled file doesn't include.
+                if let Err(e) = result {
+                    self.report_error(&source_name, "", e);
+                }
+            }
+            Err(e) => self.report_error(name, "", LoxError::Loxc(e)),
+        }
+        self.exit_on_error();
+    }
 
+    fn exit_on_error(&self) {
         if self.had_error {
             exit(65);
         }
+        if self.had_runtime_error {
+            exit(70);
+        }
     }
 
     pub fn run_promt(&mut self) {
         loop {
             print!("> ");
+            io::stdout().flush().expect("Unable to flush stdout");
             let mut line = String::new();
             match io::stdin().read_line(&mut line) {
                 Ok(x) if x > 0 => {
-                    self.run(&line);
+                    if let Err(e) = self.run(&line) {
+                        self.report_error("<repl>", &line, e);
+                    }
                     self.had_error = false;
                 }
                 _ => break,
@@ -44,32 +188,127 @@ impl Lox {
         }
     }
 
-    fn run(&self, source: &str) -> Result<(), LoxError> {
-        let tokens = Scanner::new(source.as_bytes())
+    fn run(&mut self, source: &str) -> Result<(), LoxError> {
+        let statements = self.check(source)?;
+
+        match self.options.backend {
+            Backend::Tree => self
+                .interpreter
+                .interpret(&statements)
+                .map_err(LoxError::Interpreter),
+            Backend::Vm => {
+                let script = self.compile(&statements)?;
+                self.vm.interpret(script).map_err(LoxError::Interpreter)
+            }
+        }
+    }
+
+    fn scan(&self, source: &str) -> Result<Vec<Token>, LoxError> {
+        Scanner::new(source.as_bytes())
             .scan_tokens()
-            .map_err(|e| LoxError::Scanner(e))?;
+            .map_err(LoxError::Scanner)
+    }
 
+    fn parse(&self, source: &str) -> Result<Vec<Stmt>, LoxError> {
+        let tokens = self.scan(source)?;
         let mut parser = Parser::new(&tokens);
-        let expression = parser.parse().map_err(|e| LoxError::Parser(e))?;
+        parser.parse().map_err(LoxError::Parser)
+    }
+
+    fn check(&self, source: &str) -> Result<Vec<Stmt>, LoxError> {
+        let statements = self.parse(source)?;
+
+        Resolver::new()
+            .resolve(&statements)
+            .map_err(LoxError::Resolver)?;
+
+        Ok(statements)
+    }
+
+    fn tokens(&self, source: &str) -> Result<(), LoxError> {
+        for token in self.scan(source)? {
+            let position = format!("{}:{}", token.span.line, token.span.column);
+            println!("{position:<8}{:<12} {:?}", token.lexeme, token.ttype);
+        }
+        Ok(())
+    }
+
+    fn compile(&self, statements: &[Stmt]) -> Result<Rc<Function>, LoxError> {
+        Compiler::new()
+            .compile(statements)
+            .map_err(LoxError::Compiler)
+    }
 
-        println!("{}", AstPrinter.print(&expression));
+    fn disassemble(&self, source: &str) -> Result<(), LoxError> {
+        let statements = self.check(source)?;
+        let script = self.compile(&statements)?;
+        print!("{}", debug::disassemble_function(&script));
         Ok(())
     }
 
-    fn error(&mut self, line: usize, message: &str) {
-        self.report(line, "", message);
+    fn write_bytecode(&self, name: &str, source: &str) -> Result<(), LoxError> {
+        let statements = self.check(source)?;
+        let script = self.compile(&statements)?;
+        let output = self
+            .options
+            .output
+            .as_deref()
+            .expect("`lox compile` needs an output path");
+        if let Err(e) = fs::write(output, loxc::write(name, &script)) {
+            eprintln!("Unable to write {output}: {e}");
+            exit(73);
+        }
+        Ok(())
     }
 
-    fn report(&mut self, line: usize, location: &str, message: &str) {
-        println!("[line {line}] Error{location}: {message}");
-        self.had_error = true;
+    fn ast(&self, source: &str) -> Result<(), LoxError> {
+        if self.options.from_sexpr {
+            let expr = ast_sexpr::read(source).map_err(LoxError::Sexpr)?;
+            let output = match self.options.ast_format {
+                AstFormat::Lisp => AstPrinter.print(&expr) + "\n",
+                AstFormat::Json => JsonPrinter.print(&expr) + "\n",
+                AstFormat::Sexpr => SexprPrinter.print(&expr) + "\n",
+                AstFormat::Dot => DotPrinter::new().print(&expr),
+            };
+            print!("{output}");
+            return Ok(());
+        }
+
+        let statements = self.parse(source)?;
+        match self.options.ast_format {
+            AstFormat::Lisp => print!("{}", AstPrinter.print_program(&statements)),
+            AstFormat::Json => println!("{}", JsonPrinter.print_program(&statements)),
+            AstFormat::Sexpr => print!("{}", SexprPrinter.print_program(&statements)),
+            AstFormat::Dot => print!("{}", DotPrinter::new().print_program(&statements)),
+        }
+        Ok(())
     }
 
-    fn error_token(&mut self, token: &Token, message: &str) {
-        if token.ttype == TokenType::Eof {
-            self.report(token.line, " at end", message)
-        } else {
-            self.report(token.line, &(" at '".to_string() + &token.lexeme), message)
+    /// Prints diagnostics for `error`, which was raised while running
+    /// `source` from the file called `name`.
+    fn report_error(&mut self, name: &str, source: &str, error: LoxError) {
+        let diagnostics: Vec<Diagnostic> = match &error {
+            LoxError::Sexpr(e) => vec![e.diagnostic()],
+            LoxError::Scanner(errors) => errors.iter().map(ScannerError::diagnostic).collect(),
+            LoxError::Parser(errors) => errors.iter().map(ParserError::diagnostic).collect(),
+            LoxError::Resolver(errors) => errors.iter().map(ResolverError::diagnostic).collect(),
+            LoxError::Compiler(errors) => errors.iter().map(CompilerError::diagnostic).collect(),
+            LoxError::Loxc(e) => vec![e.diagnostic()],
+            LoxError::Interpreter(e) => vec![e.diagnostic()],
+        };
+
+        for diagnostic in diagnostics {
+            match self.options.error_format {
+                ErrorFormat::Human => {
+                    eprint!("{}", diagnostic.render(name, source, self.options.color))
+                }
+                ErrorFormat::Json => eprintln!("{}", diagnostic.to_json(name, source)),
+            }
+        }
+
+        match error {
+            LoxError::Interpreter(_) => self.had_runtime_error = true,
+            _ => self.had_error = true,
         }
     }
 }
@@ -0,0 +1,668 @@
+use std::rc::Rc;
+
+use thiserror::Error;
+
+use crate::{
+    chunk::{Chunk, Constant, Function, OpCode},
+    diagnostic::Diagnostic,
+    symbol::Symbol,
+    token::Span,
+};
+
+/// The first bytes of every compiled Lox file.
+pub const MAGIC: &[u8; 4] = b"LOXC";
+
+/// The version of the format written by [`write`]. Bump it whenever the
+/// layout below or the meaning of any opcode changes, so that old files are
+/// rejected instead of misbehaving.
+pub const VERSION: u16 = 2;
+
+const NUMBER: u8 = 0;
+const STRING: u8 = 1;
+const FUNCTION: u8 = 2;
+const NAME: u8 = 3;
+
+#[derive(Error, Debug)]
+pub enum LoxcError {
+    #[error("Not a compiled Lox file.")]
+    NotBytecode,
+
+    #[error("Bytecode format version {found} is not supported; this lox reads version {VERSION}.")]
+    UnsupportedVersion { found: u16 },
+
+    #[error("Checksum mismatch; the file is corrupt.")]
+    ChecksumMismatch,
+
+    #[error("Bytecode ends unexpectedly.")]
+    Truncated,
+
+    #[error("Malformed bytecode: {reason}.")]
+    Malformed { reason: String },
+}
+
+impl LoxcError {
+    /// A stable identifier for the kind of error, used in JSON diagnostics.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotBytecode => "E0600",
+            Self::UnsupportedVersion { .. } => "E0601",
+            Self::ChecksumMismatch => "E0602",
+            Self::Truncated => "E0603",
+            Self::Malformed { .. } => "E0604",
+        }
+    }
+
+    /// Bytecode has no source to point at, so the diagnostic has no span.
+    pub fn diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::error(self.code(), &self.to_string(), Span::default());
+        match self {
+            Self::UnsupportedVersion { .. } => {
+                diagnostic.with_note("recompile the script with `lox compile`")
+            }
+            _ => diagnostic,
+        }
+    }
+}
+
+/// Whether `bytes` start like a compiled Lox file.
+pub fn is_bytecode(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Serializes `script` and the name of the file it was compiled from.
+///
+/// The file is the magic bytes, the format version as a big-endian `u16`, a
+/// CRC-32 of the rest of the file as a big-endian `u32`, then the payload:
+/// the source name followed by the script. Integers in the payload are
+/// big-endian `u32`s, strings are a length followed by UTF-8 bytes, and
+/// numbers are the bits of an `f64`.
+pub fn write(source_name: &str, script: &Function) -> Vec<u8> {
+    let mut payload = Vec::new();
+    write_string(&mut payload, source_name);
+    write_function(&mut payload, script);
+
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&VERSION.to_be_bytes());
+    bytes.extend_from_slice(&crc32(&payload).to_be_bytes());
+    bytes.extend_from_slice(&payload);
+    bytes
+}
+
+/// Reads a file written by [`write`], returning the name of the source file
+/// and the script.
+pub fn read(bytes: &[u8]) -> Result<(String, Rc<Function>), LoxcError> {
+    if !is_bytecode(bytes) {
+        return Err(LoxcError::NotBytecode);
+    }
+    let mut reader = Reader {
+        bytes,
+        current: MAGIC.len(),
+    };
+
+    let version = u16::from_be_bytes([reader.byte()?, reader.byte()?]);
+    if version != VERSION {
+        return Err(LoxcError::UnsupportedVersion { found: version });
+    }
+    let checksum = reader.u32()?;
+    if crc32(&bytes[reader.current..]) != checksum {
+        return Err(LoxcError::ChecksumMismatch);
+    }
+
+    let source_name = reader.string()?.to_string();
+    let script = reader.function()?;
+    if reader.current != bytes.len() {
+        return Err(LoxcError::Malformed {
+            reason: "trailing bytes after the script".to_string(),
+        });
+    }
+    if script.arity != 0 || script.upvalue_count != 0 {
+        return Err(LoxcError::Malformed {
+            reason: "the script takes arguments or captures variables".to_string(),
+        });
+    }
+    verify(&script)?;
+    Ok((source_name, script))
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: usize) {
+    let value = u32::try_from(value).expect("Value too large for a bytecode file");
+    bytes.extend_from_slice(&value.to_be_bytes());
+}
+
+fn write_string(bytes: &mut Vec<u8>, string: &str) {
+    write_u32(bytes, string.len());
+    bytes.extend_from_slice(string.as_bytes());
+}
+
+fn write_function(bytes: &mut Vec<u8>, function: &Function) {
+    write_string(bytes, &function.name);
+    write_u32(bytes, function.arity);
+    write_u32(bytes, function.upvalue_count);
+    write_chunk(bytes, &function.chunk);
+}
+
+fn write_chunk(bytes: &mut Vec<u8>, chunk: &Chunk) {
+    write_u32(bytes, chunk.code.len());
+    bytes.extend_from_slice(&chunk.code);
+
+    write_u32(bytes, chunk.constants.len());
+    for constant in &chunk.constants {
+        match constant {
+            Constant::Number(number) => {
+                bytes.push(NUMBER);
+                bytes.extend_from_slice(&number.to_bits().to_be_bytes());
+            }
+            Constant::String(string) => {
+                bytes.push(STRING);
+                write_string(bytes, string);
+            }
+            Constant::Name(name) => {
+                bytes.push(NAME);
+                write_string(bytes, name);
+            }
+            Constant::Function(function) => {
+                bytes.push(FUNCTION);
+                write_function(bytes, function);
+            }
+        }
+    }
+
+    write_u32(bytes, chunk.spans.len());
+    for (offset, span) in &chunk.spans {
+        write_u32(bytes, *offset);
+        write_u32(bytes, span.start);
+        write_u32(bytes, span.end);
+        write_u32(bytes, span.line);
+        write_u32(bytes, span.column);
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    current: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], LoxcError> {
+        let end = self
+            .current
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or(LoxcError::Truncated)?;
+        let bytes = &self.bytes[self.current..end];
+        self.current = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, LoxcError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, LoxcError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn usize(&mut self) -> Result<usize, LoxcError> {
+        Ok(self.u32()? as usize)
+    }
+
+    fn string(&mut self) -> Result<&'a str, LoxcError> {
+        let len = self.usize()?;
+        std::str::from_utf8(self.take(len)?).map_err(|_| LoxcError::Malformed {
+            reason: "string is not valid UTF-8".to_string(),
+        })
+    }
+
+    fn function(&mut self) -> Result<Rc<Function>, LoxcError> {
+        let name = self.string()?.into();
+        let arity = self.usize()?;
+        let upvalue_count = self.usize()?;
+        let chunk = self.chunk()?;
+        Ok(Rc::new(Function {
+            name,
+            arity,
+            upvalue_count,
+            chunk,
+        }))
+    }
+
+    fn chunk(&mut self) -> Result<Chunk, LoxcError> {
+        let len = self.usize()?;
+        let code = self.take(len)?.to_vec();
+
+        // Lengths come from the file, so they aren't trusted for allocation.
+        let mut constants = Vec::new();
+        for _ in 0..self.usize()? {
+            let constant = match self.byte()? {
+                NUMBER => {
+                    let bytes = self.take(8)?.try_into().expect("took eight bytes");
+                    Constant::Number(f64::from_bits(u64::from_be_bytes(bytes)))
+                }
+                STRING => Constant::String(self.string()?.into()),
+                NAME => Constant::Name(Symbol::intern(self.string()?)),
+                FUNCTION => Constant::Function(self.function()?),
+                tag => {
+                    return Err(LoxcError::Malformed {
+                        reason: format!("unknown constant tag {tag}"),
+                    })
+                }
+            };
+            constants.push(constant);
+        }
+
+        let mut spans = Vec::new();
+        for _ in 0..self.usize()? {
+            let offset = self.usize()?;
+            let span = Span {
+                start: self.usize()?,
+                end: self.usize()?,
+                line: self.usize()?,
+                column: self.usize()?,
+            };
+            spans.push((offset, span));
+        }
+
+        Ok(Chunk {
+            code,
+            constants,
+            spans,
+        })
+    }
+}
+
+/// Checks that running `function` and the functions nested in it can't send
+/// the VM outside its code, constant pool, stack or upvalues. The checksum
+/// only catches corruption; this catches files that were built wrong.
+fn verify(function: &Function) -> Result<(), LoxcError> {
+    let chunk = &function.chunk;
+    let malformed = |offset: usize, problem: &str| LoxcError::Malformed {
+        reason: format!("{problem} at offset {offset} in `{}`", function.name),
+    };
+
+    // Decode every instruction, checking operands against the chunk.
+    let mut instructions = Vec::new();
+    let mut is_start = vec![false; chunk.code.len()];
+    let mut offset = 0;
+    while offset < chunk.code.len() {
+        is_start[offset] = true;
+        let instruction =
+            decode(function, offset).map_err(|problem| malformed(offset, &problem))?;
+        offset = instruction.next;
+        instructions.push(instruction);
+    }
+    for instruction in &instructions {
+        if let Some(target) = instruction.jump {
+            if !is_start.get(target).copied().unwrap_or(false) {
+                return Err(malformed(
+                    instruction.offset,
+                    "jump doesn't land on an instruction",
+                ));
+            }
+        }
+    }
+
+    // Follow every path through the code to find how deep the stack is at
+    // each instruction, so local slots can be checked against it.
+    let index_of = |offset: usize| {
+        instructions
+            .binary_search_by_key(&offset, |instruction| instruction.offset)
+            .expect("jumps land on instructions")
+    };
+    let mut depths: Vec<Option<usize>> = vec![None; instructions.len()];
+    let mut pending = vec![(0, 1 + function.arity)];
+    while let Some((offset, depth)) = pending.pop() {
+        if offset == chunk.code.len() {
+            return Err(malformed(offset, "code runs past the end"));
+        }
+        let index = index_of(offset);
+        match depths[index] {
+            Some(known) if known == depth => continue,
+            Some(_) => return Err(malformed(offset, "stack depth differs between paths")),
+            None => depths[index] = Some(depth),
+        }
+
+        let instruction = &instructions[index];
+        if instruction.pops > depth {
+            return Err(malformed(offset, "stack underflow"));
+        }
+        if instruction.locals.iter().any(|&slot| slot >= depth) {
+            return Err(malformed(offset, "local slot out of range"));
+        }
+        let depth = depth - instruction.pops + instruction.pushes;
+        match instruction.op {
+            OpCode::Return => {}
+            OpCode::Jump | OpCode::Loop => {
+                pending.push((instruction.jump.expect("jumps have targets"), depth))
+            }
+            OpCode::JumpIfFalse => {
+                pending.push((instruction.jump.expect("jumps have targets"), depth));
+                pending.push((instruction.next, depth));
+            }
+            _ => pending.push((instruction.next, depth)),
+        }
+    }
+
+    for constant in &chunk.constants {
+        if let Constant::Function(nested) = constant {
+            verify(nested)?;
+        }
+    }
+    Ok(())
+}
+
+/// What the verifier needs to know about one instruction.
+struct Instruction {
+    op: OpCode,
+    offset: usize,
+    next: usize,
+    jump: Option<usize>,
+    pops: usize,
+    pushes: usize,
+    /// Local slots the instruction reads, writes or captures.
+    locals: Vec<usize>,
+}
+
+fn decode(function: &Function, offset: usize) -> Result<Instruction, String> {
+    let chunk = &function.chunk;
+    let code = &chunk.code;
+    let op = OpCode::from_byte(code[offset])
+        .ok_or_else(|| format!("invalid opcode {}", code[offset]))?;
+    let operand = |index: usize| {
+        code.get(offset + 1 + index)
+            .copied()
+            .ok_or_else(|| "operands run past the end of the code".to_string())
+    };
+    let constant = || -> Result<&Constant, String> {
+        let index = u16::from_be_bytes([operand(0)?, operand(1)?]) as usize;
+        chunk
+            .constants
+            .get(index)
+            .ok_or_else(|| format!("constant {index} out of range"))
+    };
+    let name = || match constant()? {
+        Constant::Name(_) => Ok(()),
+        _ => Err("expected a name constant".to_string()),
+    };
+    let upvalue = |index: u8| {
+        if (index as usize) < function.upvalue_count {
+            Ok(())
+        } else {
+            Err(format!("upvalue {index} out of range"))
+        }
+    };
+
+    let mut instruction = Instruction {
+        op,
+        offset,
+        next: offset + 1,
+        jump: None,
+        pops: 0,
+        pushes: 0,
+        locals: Vec::new(),
+    };
+    let (pops, pushes, operands) = match op {
+        OpCode::Constant => match constant()? {
+            Constant::Number(_) | Constant::String(_) => (0, 1, 2),
+            _ => return Err("expected a number or string constant".to_string()),
+        },
+        OpCode::Nil | OpCode::True | OpCode::False => (0, 1, 0),
+        OpCode::Pop | OpCode::Print | OpCode::CloseUpvalue => (1, 0, 0),
+        OpCode::GetLocal | OpCode::SetLocal => {
+            instruction.locals.push(operand(0)? as usize);
+            if op == OpCode::GetLocal {
+                (0, 1, 1)
+            } else {
+                (1, 1, 1)
+            }
+        }
+        OpCode::GetGlobal | OpCode::Class => {
+            name()?;
+            (0, 1, 2)
+        }
+        OpCode::DefineGlobal => {
+            name()?;
+            (1, 0, 2)
+        }
+        OpCode::SetGlobal | OpCode::GetProperty => {
+            name()?;
+            (1, 1, 2)
+        }
+        OpCode::SetProperty | OpCode::GetSuper | OpCode::Method => {
+            name()?;
+            (2, 1, 2)
+        }
+        OpCode::GetUpvalue => {
+            upvalue(operand(0)?)?;
+            (0, 1, 1)
+        }
+        OpCode::SetUpvalue => {
+            upvalue(operand(0)?)?;
+            (1, 1, 1)
+        }
+        OpCode::Equal
+        | OpCode::NotEqual
+        | OpCode::Greater
+        | OpCode::GreaterEqual
+        | OpCode::Less
+        | OpCode::LessEqual
+        | OpCode::Add
+        | OpCode::Subtract
+        | OpCode::Multiply
+        | OpCode::Divide
+        | OpCode::Inherit => (2, 1, 0),
+        OpCode::Not | OpCode::Negate => (1, 1, 0),
+        OpCode::Jump | OpCode::JumpIfFalse | OpCode::Loop => {
+            let jump = u16::from_be_bytes([operand(0)?, operand(1)?]) as usize;
+            let after = offset + 3;
+            instruction.jump = Some(if op == OpCode::Loop {
+                after
+                    .checked_sub(jump)
+                    .ok_or_else(|| "loop jumps before the start of the code".to_string())?
+            } else {
+                after + jump
+            });
+            let pops = if op == OpCode::JumpIfFalse { 1 } else { 0 };
+            (pops, pops, 2)
+        }
+        OpCode::Call => {
+            let argument_count = operand(0)? as usize;
+            (argument_count + 1, 1, 1)
+        }
+        OpCode::Closure => {
+            let Constant::Function(nested) = constant()? else {
+                return Err("expected a function constant".to_string());
+            };
+            for pair in 0..nested.upvalue_count {
+                let is_local = operand(2 + pair * 2)?;
+                let index = operand(3 + pair * 2)?;
+                match is_local {
+                    1 => instruction.locals.push(index as usize),
+                    0 => upvalue(index)?,
+                    _ => return Err(format!("invalid upvalue kind {is_local}")),
+                }
+            }
+            (0, 1, 2 + nested.upvalue_count * 2)
+        }
+        OpCode::Return => (1, 0, 0),
+    };
+    instruction.pops = pops;
+    instruction.pushes = pushes;
+    instruction.next = offset + 1 + operands;
+    Ok(instruction)
+}
+
+/// The CRC-32 (IEEE) checksum of `bytes`.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::Span;
+
+    /// A script that prints `1` and returns, followed by `code` if given.
+    fn script(code: &[u8]) -> Function {
+        let mut chunk = Chunk::default();
+        chunk.add_constant(Constant::Number(1.0));
+        let code = if code.is_empty() {
+            &[
+                OpCode::Constant as u8,
+                0,
+                0,
+                OpCode::Print as u8,
+                OpCode::Nil as u8,
+                OpCode::Return as u8,
+            ][..]
+        } else {
+            code
+        };
+        for &byte in code {
+            chunk.write(byte, Span::default());
+        }
+        Function {
+            name: "script".into(),
+            arity: 0,
+            upvalue_count: 0,
+            chunk,
+        }
+    }
+
+    /// Recomputes the checksum after the payload of `bytes` was edited.
+    fn reseal(bytes: &mut [u8]) {
+        let checksum = crc32(&bytes[10..]);
+        bytes[6..10].copy_from_slice(&checksum.to_be_bytes());
+    }
+
+    fn read_code(code: &[u8]) -> Result<(String, Rc<Function>), LoxcError> {
+        read(&write("test.lox", &script(code)))
+    }
+
+    fn assert_malformed(code: &[u8], problem: &str) {
+        match read_code(code) {
+            Err(LoxcError::Malformed { reason }) => {
+                assert!(reason.contains(problem), "unexpected reason: {reason}")
+            }
+            other => panic!("expected malformed bytecode, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn round_trips_a_script() {
+        let original = script(&[]);
+        let (source_name, read) = read(&write("test.lox", &original)).unwrap();
+        assert_eq!(source_name, "test.lox");
+        assert_eq!(read.chunk.code, original.chunk.code);
+        assert!(matches!(read.chunk.constants[..], [Constant::Number(n)] if n == 1.0));
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut bytes = write("test.lox", &script(&[]));
+        bytes[0] = b'X';
+        assert!(matches!(read(&bytes), Err(LoxcError::NotBytecode)));
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut bytes = write("test.lox", &script(&[]));
+        bytes[4..6].copy_from_slice(&(VERSION + 1).to_be_bytes());
+        assert!(matches!(
+            read(&bytes),
+            Err(LoxcError::UnsupportedVersion { found }) if found == VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn rejects_bad_checksum() {
+        let mut bytes = write("test.lox", &script(&[]));
+        *bytes.last_mut().unwrap() ^= 1;
+        assert!(matches!(read(&bytes), Err(LoxcError::ChecksumMismatch)));
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let bytes = write("test.lox", &script(&[]));
+        assert!(matches!(read(&bytes[..5]), Err(LoxcError::Truncated)));
+
+        let mut bytes = bytes[..bytes.len() - 1].to_vec();
+        reseal(&mut bytes);
+        assert!(matches!(read(&bytes), Err(LoxcError::Truncated)));
+    }
+
+    #[test]
+    fn rejects_trailing_bytes() {
+        let mut bytes = write("test.lox", &script(&[]));
+        bytes.push(0);
+        reseal(&mut bytes);
+        assert!(matches!(read(&bytes), Err(LoxcError::Malformed { .. })));
+    }
+
+    #[test]
+    fn rejects_invalid_opcodes() {
+        assert_malformed(&[200], "invalid opcode");
+    }
+
+    #[test]
+    fn rejects_missing_operands() {
+        assert_malformed(&[OpCode::Constant as u8, 0], "operands run past the end");
+    }
+
+    #[test]
+    fn rejects_constants_out_of_range() {
+        assert_malformed(
+            &[
+                OpCode::Constant as u8,
+                0xFF,
+                0xFF,
+                OpCode::Return as u8,
+            ],
+            "constant 65535 out of range",
+        );
+    }
+
+    #[test]
+    fn rejects_jumps_into_operands() {
+        assert_malformed(
+            &[
+                OpCode::Jump as u8,
+                0,
+                1,
+                OpCode::Constant as u8,
+                0,
+                0,
+                OpCode::Return as u8,
+            ],
+            "jump doesn't land on an instruction",
+        );
+    }
+
+    #[test]
+    fn rejects_jumps_past_the_end() {
+        assert_malformed(&[OpCode::Jump as u8, 0, 9, OpCode::Nil as u8], "jump doesn't land");
+    }
+
+    #[test]
+    fn rejects_locals_above_the_stack() {
+        assert_malformed(&[OpCode::GetLocal as u8, 5, OpCode::Return as u8], "local slot out of range");
+    }
+
+    #[test]
+    fn rejects_stack_underflow() {
+        assert_malformed(&[OpCode::Pop as u8, OpCode::Pop as u8], "stack underflow");
+    }
+
+    #[test]
+    fn rejects_code_that_runs_past_the_end() {
+        assert_malformed(&[OpCode::Nil as u8], "code runs past the end");
+    }
+}
@@ -1,63 +1,298 @@
+mod ast_dot;
+mod ast_json;
 mod ast_printer;
+mod ast_sexpr;
 mod expr;
 mod lox;
 mod parser;
 pub mod scanner;
+pub mod symbol;
 pub mod token;
 mod literal;
 mod object;
 mod interpreter;
+mod stmt;
+mod environment;
+mod callable;
+mod resolver;
+mod class;
+mod diagnostic;
+mod chunk;
+mod debug;
+mod compiler;
+mod value;
+mod vm;
+mod json;
+mod loxc;
 
-use std::{env, fs, io, process::exit};
+use std::{
+    env, fs,
+    io::{self, IsTerminal, Read},
+    panic,
+    path::Path,
+    process::exit,
+    thread,
+};
 
-use ast_printer::AstPrinter;
-use expr::{BinaryData, Expr, GroupingData, UnaryData};
-use lox::Lox;
-use token::{Token, TokenType};
+use lox::{AstFormat, Backend, Command, ErrorFormat, Lox, Options};
+use value::DEFAULT_GROWTH_FACTOR;
+use vm::FRAMES_MAX;
 
-use crate::{literal::Literal, scanner::Scanner};
+/// Where the script comes from.
+enum Input {
+    File(String),
+    Stdin,
+    Inline(String),
+}
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
+/// Native stack to set aside for each Lox call. The tree-walking interpreter
+/// recurses on the native stack, and in a debug build a call made from a few
+/// blocks deep takes about 14 KiB of it. Expressions nested far deeper than
+/// that inside a recursive function can still overflow the native stack
+/// before `FRAMES_MAX` calls.
+const STACK_PER_FRAME: usize = 64 * 1024;
+
+/// Enough native stack for the interpreter to reach `FRAMES_MAX` calls and
+/// report the stack overflow itself.
+const STACK_SIZE: usize = FRAMES_MAX * STACK_PER_FRAME;
 
-    let mut lox = Lox::new();
-    if args.len() > 2 {
-        println!("Usage: lox [script]");
-        exit(64);
-    } else if args.len() == 2 {
-        lox.run_file(&args[1]);
-    } else {
-        lox.run_promt();
+fn main() {
+    let interpreter = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)
+        .expect("Unable to start the interpreter thread");
+    if let Err(panic) = interpreter.join() {
+        panic::resume_unwind(panic);
     }
 }
 
-fn test_ast_printer() {
-    let udata = UnaryData {
-        operator: Token {
-            ttype: token::TokenType::Minus,
-            lexeme: "-".to_string(),
-            line: 1,
-        },
-        right: Expr::Literal(Literal::Number(123.0)).into(),
+fn run() {
+    let mut options = Options {
+        color: io::stderr().is_terminal(),
+        error_format: ErrorFormat::Human,
+        ast_format: AstFormat::Lisp,
+        from_sexpr: false,
+        backend: Backend::Tree,
+        trace_execution: false,
+        output: None,
+        gc_stress: false,
+        gc_growth_factor: DEFAULT_GROWTH_FACTOR,
     };
-    let unary = Expr::Unary(udata);
+    let mut command = None;
+    let mut input = None;
 
-    let gdata = GroupingData {
-        expression: Expr::Literal(Literal::Bool(false)).into(),
-    };
-    let group = Expr::Grouping(gdata);
-
-    let bdata = BinaryData {
-        left: unary.into(),
-        operator: Token {
-            ttype: token::TokenType::Star,
-            lexeme: "*".to_string(),
-            line: 1,
-        },
-        right: group.into(),
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let next_input = match arg.as_str() {
+            "--no-color" => {
+                options.color = false;
+                continue;
+            }
+            "--error-format=human" => {
+                options.error_format = ErrorFormat::Human;
+                continue;
+            }
+            "--error-format=json" => {
+                options.error_format = ErrorFormat::Json;
+                continue;
+            }
+            "--ast-format=lisp" => {
+                options.ast_format = AstFormat::Lisp;
+                continue;
+            }
+            "--ast-format=json" => {
+                options.ast_format = AstFormat::Json;
+                continue;
+            }
+            "--ast-format=sexpr" => {
+                options.ast_format = AstFormat::Sexpr;
+                continue;
+            }
+            "--ast-format=dot" => {
+                options.ast_format = AstFormat::Dot;
+                continue;
+            }
+            "--backend=tree" => {
+                options.backend = Backend::Tree;
+                continue;
+            }
+            "--backend=vm" => {
+                options.backend = Backend::Vm;
+                continue;
+            }
+            "--trace-execution" => {
+                options.backend = Backend::Vm;
+                options.trace_execution = true;
+                continue;
+            }
+            "--gc-stress" => {
+                options.gc_stress = true;
+                continue;
+            }
+            _ if arg.starts_with("--gc-growth=") => {
+                options.gc_growth_factor = arg["--gc-growth=".len()..]
+                    .parse()
+                    .ok()
+                    .filter(|&factor: &f64| factor >= 1.0)
+                    .unwrap_or_else(|| usage());
+                continue;
+            }
+            "--from-sexpr" => {
+                options.from_sexpr = true;
+                continue;
+            }
+            "-o" => {
+                options.output = Some(args.next().unwrap_or_else(|| usage()));
+                continue;
+            }
+            "-e" => Input::Inline(args.next().unwrap_or_else(|| usage())),
+            "-" => Input::Stdin,
+            _ if arg.starts_with('-') => usage(),
+            _ => {
+                if command.is_none() && input.is_none() {
+                    if let Some(subcommand) = subcommand(&arg) {
+                        command = Some(subcommand);
+                        continue;
+                    }
+                }
+                Input::File(arg)
+            }
+        };
+
+        if input.replace(next_input).is_some() {
+            usage();
+        }
+    }
+
+    let command = command.unwrap_or(Command::Run);
+    if command == Command::Compile && options.output.is_none() {
+        match &input {
+            Some(Input::File(path)) => {
+                let output = Path::new(path).with_extension("loxc");
+                options.output = Some(output.to_string_lossy().into_owned());
+            }
+            _ => usage(),
+        }
+    }
+    let mut lox = Lox::new(options);
+    match input {
+        Some(Input::File(path)) => {
+            let bytes = fs::read(&path).unwrap_or_else(|e| {
+                eprintln!("Unable to read {path}: {e}");
+                exit(66);
+            });
+            if path.ends_with(".loxc") || loxc::is_bytecode(&bytes) {
+                if !matches!(command, Command::Run | Command::Disassemble) {
+                    eprintln!("{path} is compiled bytecode; only `run` and `disassemble` accept it");
+                    exit(64);
+                }
+                lox.run_bytecode(command, &path, &bytes);
+                return;
+            }
+            let source = String::from_utf8(bytes).unwrap_or_else(|e| {
+                eprintln!("Unable to read {path}: {e}");
+                exit(66);
+            });
+            lox.run_script(command, &path, &source);
+        }
+        Some(Input::Stdin) => {
+            let mut source = String::new();
+            if let Err(e) = io::stdin().read_to_string(&mut source) {
+                eprintln!("Unable to read stdin: {e}");
+                exit(66);
+            }
+            lox.run_script(command, "<stdin>", &source);
+        }
+        Some(Input::Inline(source)) => lox.run_script(command, "<inline>", &source),
+        None if command == Command::Run => lox.run_promt(),
+        None => usage(),
+    }
+}
+
+fn subcommand(name: &str) -> Option<Command> {
+    match name {
+        "run" => Some(Command::Run),
+        "tokens" => Some(Command::Tokens),
+        "ast" => Some(Command::Ast),
+        "check" => Some(Command::Check),
+        "disassemble" => Some(Command::Disassemble),
+        "compile" => Some(Command::Compile),
+        _ => None,
+    }
+}
+
+fn usage() -> ! {
+    println!("Usage: lox [command] [options] [script | -e <code> | -]");
+    println!();
+    println!("Commands:");
+    println!("  run       Run the script, or start a REPL without one (default)");
+    println!("  tokens    Print the tokens the scanner produces");
+    println!("  ast       Print the syntax tree");
+    println!("  check     Report errors without running the script");
+    println!("  disassemble");
+    println!("            Print the bytecode the script compiles to");
+    println!("  compile   Write the script's bytecode to a .loxc file");
+    println!();
+    println!("Options:");
+    println!("  -e <code>                   Use <code> as the script");
+    println!("  -                           Read the script from stdin");
+    println!("  -o <file>                   Where `compile` writes the bytecode");
+    println!("  --no-color                  Don't color error messages");
+    println!("  --error-format=human|json   How to print errors");
+    println!("  --ast-format=lisp|json|sexpr|dot");
+    println!("                              How `ast` prints the syntax tree");
+    println!("  --backend=tree|vm           Walk the syntax tree or run bytecode");
+    println!("  --trace-execution           Run on the VM, printing each instruction");
+    println!("  --gc-stress                 Collect garbage after every VM allocation");
+    println!("  --gc-growth=<factor>        Heap growth between VM collections (default 2)");
+    println!("  --from-sexpr                Read the script as an S-expression for `ast`");
+    exit(64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        interpreter::{Interpreter, InterpreterError},
+        parser::Parser,
+        resolver::Resolver,
+        scanner::Scanner,
     };
-    let binary = Expr::Binary(bdata);
 
-    let output = AstPrinter.print(&binary);
-    println!("{output}")
+    /// Interprets `source` on a thread with the same stack as `main` gives
+    /// the interpreter.
+    fn interpret(source: &'static str) -> Result<(), InterpreterError> {
+        thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(move || {
+                let tokens = Scanner::new(source.as_bytes()).scan_tokens().unwrap();
+                let statements = Parser::new(&tokens).parse().unwrap();
+                Resolver::new().resolve(&statements).unwrap();
+                Interpreter::new().interpret(&statements)
+            })
+            .unwrap()
+            .join()
+            .unwrap()
+    }
+
+    #[test]
+    fn functions_reach_frames_max_within_stack_size() {
+        let result = interpret(
+            "fun down(n) { { { { if (n >= 0) return down(n + 1) + 1; } } } }
+             down(0);",
+        );
+        assert!(matches!(result, Err(InterpreterError::StackOverflow { .. })));
+    }
+
+    #[test]
+    fn methods_reach_frames_max_within_stack_size() {
+        let result = interpret(
+            "class Node {
+               init(depth) { this.depth = depth; }
+               down() { { { { if (this.depth >= 0) return Node(this.depth + 1).down() + 1; } } } }
+             }
+             Node(0).down();",
+        );
+        assert!(matches!(result, Err(InterpreterError::StackOverflow { .. })));
+    }
 }
@@ -1,6 +1,93 @@
-use crate::literal::Literal;
+use std::{cell::RefCell, fmt, rc::Rc};
 
-#[derive(Debug, Clone)]
-pub enum Object{
-    Literal(Literal)
-}
\ No newline at end of file
+use crate::{
+    callable::{LoxCallable, LoxFunction, NativeFunction},
+    class::{LoxClass, LoxInstance},
+    literal::Literal,
+};
+
+#[derive(Clone)]
+pub enum Object {
+    /// A value written in the source. Its strings are interned.
+    Literal(Literal),
+    /// A string built while the program runs, like the result of `+`. These
+    /// aren't interned, so they are freed once nothing refers to them.
+    String(Rc<str>),
+    Function(Rc<LoxFunction>),
+    NativeFunction(Rc<NativeFunction>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+}
+
+impl Object {
+    pub fn is_truthy(&self) -> bool {
+        !matches!(
+            self,
+            Object::Literal(Literal::Nil) | Object::Literal(Literal::Bool(false))
+        )
+    }
+
+    pub fn as_callable(&self) -> Option<&dyn LoxCallable> {
+        match self {
+            Object::Function(function) => Some(function.as_ref()),
+            Object::NativeFunction(function) => Some(function.as_ref()),
+            Object::Class(class) => Some(class),
+            Object::Literal(_) | Object::String(_) | Object::Instance(_) => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Object::Literal(Literal::String(string)) => Some(string.as_str()),
+            Object::String(string) => Some(string),
+            _ => None,
+        }
+    }
+}
+
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Object::Literal(l), Object::Literal(r)) => l == r,
+            (Object::String(_), _) | (_, Object::String(_)) => {
+                self.as_str().is_some() && self.as_str() == other.as_str()
+            }
+            (Object::Function(l), Object::Function(r)) => Rc::ptr_eq(l, r),
+            (Object::NativeFunction(l), Object::NativeFunction(r)) => Rc::ptr_eq(l, r),
+            (Object::Class(l), Object::Class(r)) => Rc::ptr_eq(l, r),
+            (Object::Instance(l), Object::Instance(r)) => Rc::ptr_eq(l, r),
+            _ => false,
+        }
+    }
+}
+
+impl From<f64> for Object {
+    fn from(value: f64) -> Self {
+        Object::Literal(Literal::Number(value))
+    }
+}
+
+impl From<bool> for Object {
+    fn from(value: bool) -> Self {
+        Object::Literal(Literal::Bool(value))
+    }
+}
+
+impl From<String> for Object {
+    fn from(value: String) -> Self {
+        Object::String(value.into())
+    }
+}
+
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Object::Literal(literal) => write!(f, "{literal}"),
+            Object::String(string) => write!(f, "{string}"),
+            Object::Function(function) => write!(f, "{function}"),
+            Object::NativeFunction(function) => write!(f, "{function}"),
+            Object::Class(class) => write!(f, "{class}"),
+            Object::Instance(instance) => write!(f, "{}", instance.borrow()),
+        }
+    }
+}
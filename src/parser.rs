@@ -1,28 +1,741 @@
+use std::rc::Rc;
+
+use thiserror::Error;
+
 use crate::{
-    expr::*, literal::Literal, token::{Token, TokenType}
+    diagnostic::Diagnostic,
+    expr::*,
+    literal::Literal,
+    stmt::*,
+    token::{Span, Token, TokenType},
 };
 
-#[derive(Debug)]
+#[derive(Error, Debug)]
 pub enum ParserError {
-    Custom { message: String, token: Token },
+    #[error("Expect expression.")]
+    ExpectedExpression { token: Token },
+
+    #[error("Invalid assignment target.")]
+    InvalidAssignmentTarget { equals: Token, target: Span },
+
+    #[error("Expect ';' after expression.")]
+    ExpectedSemicolonAfterExpression { token: Token },
+
+    #[error("Expect '(' after 'if'.")]
+    ExpectedLeftParenAfterIf { token: Token },
+
+    #[error("Expect ')' after if condition.")]
+    ExpectedRightParenAfterIfCondition { token: Token },
+
+    #[error("Expect variable name.")]
+    ExpectedVariableName { token: Token },
+
+    #[error("Expect '.' after 'super'.")]
+    ExpectedDotAfterSuper { token: Token },
+
+    #[error("Can't use 'break' outside of a loop.")]
+    BreakOutsideLoop { keyword: Token },
+
+    #[error("Can't have more than 255 parameters.")]
+    TooManyParameters { token: Token },
+
+    #[error("Can't have more than 255 arguments.")]
+    TooManyArguments { token: Token },
+
+    #[error("Expect ':' after then branch of conditional expression.")]
+    ExpectedColon { token: Token },
+
+    #[error("Can't use 'continue' outside of a loop.")]
+    ContinueOutsideLoop { keyword: Token },
+
+    #[error("Expect ';' after value.")]
+    ExpectedSemicolonAfterValue { token: Token },
+
+    #[error("Expect ';' after variable declaration.")]
+    ExpectedSemicolonAfterVariableDeclaration { token: Token },
+
+    #[error("Expect ';' after return value.")]
+    ExpectedSemicolonAfterReturnValue { token: Token },
+
+    #[error("Expect ';' after loop condition.")]
+    ExpectedSemicolonAfterLoopCondition { token: Token },
+
+    #[error("Expect ';' after 'break'.")]
+    ExpectedSemicolonAfterBreak { token: Token },
+
+    #[error("Expect ';' after 'continue'.")]
+    ExpectedSemicolonAfterContinue { token: Token },
+
+    #[error("Expect '(' after 'while'.")]
+    ExpectedLeftParenAfterWhile { token: Token },
+
+    #[error("Expect ')' after condition.")]
+    ExpectedRightParenAfterWhileCondition { token: Token },
+
+    #[error("Expect '(' after 'for'.")]
+    ExpectedLeftParenAfterFor { token: Token },
+
+    #[error("Expect ')' after for clauses.")]
+    ExpectedRightParenAfterForClauses { token: Token },
+
+    #[error("Expect '{{' before class body.")]
+    ExpectedLeftBraceBeforeClassBody { token: Token },
+
+    #[error("Expect '}}' after class body.")]
+    ExpectedRightBraceAfterClassBody { token: Token },
+
+    #[error("Expect '(' after function name.")]
+    ExpectedLeftParenAfterFunctionName { token: Token },
+
+    #[error("Expect '(' after method name.")]
+    ExpectedLeftParenAfterMethodName { token: Token },
+
+    #[error("Expect ')' after parameters.")]
+    ExpectedRightParenAfterParameters { token: Token },
+
+    #[error("Expect '{{' before function body.")]
+    ExpectedLeftBraceBeforeFunctionBody { token: Token },
+
+    #[error("Expect '{{' before method body.")]
+    ExpectedLeftBraceBeforeMethodBody { token: Token },
+
+    #[error("Expect '}}' after block.")]
+    ExpectedRightBraceAfterBlock { token: Token },
+
+    #[error("Expect ')' after arguments.")]
+    ExpectedRightParenAfterArguments { token: Token },
+
+    #[error("Expect ')' after expression.")]
+    ExpectedRightParenAfterExpression { token: Token },
+
+    #[error("Expect class name.")]
+    ExpectedClassName { token: Token },
+
+    #[error("Expect superclass name.")]
+    ExpectedSuperclassName { token: Token },
+
+    #[error("Expect function name.")]
+    ExpectedFunctionName { token: Token },
+
+    #[error("Expect method name.")]
+    ExpectedMethodName { token: Token },
+
+    #[error("Expect parameter name.")]
+    ExpectedParameterName { token: Token },
+
+    #[error("Expect property name after '.'.")]
+    ExpectedPropertyName { token: Token },
+
+    #[error("Expect superclass method name.")]
+    ExpectedSuperclassMethodName { token: Token },
+}
+
+impl ParserError {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::InvalidAssignmentTarget { target, .. } => *target,
+            _ => self.token().span,
+        }
+    }
+
+    /// The token the parser was looking at when it gave up.
+    fn token(&self) -> &Token {
+        match self {
+            Self::InvalidAssignmentTarget { equals, .. } => equals,
+            Self::BreakOutsideLoop { keyword } | Self::ContinueOutsideLoop { keyword } => keyword,
+            Self::ExpectedExpression { token }
+            | Self::ExpectedSemicolonAfterExpression { token }
+            | Self::ExpectedLeftParenAfterIf { token }
+            | Self::ExpectedRightParenAfterIfCondition { token }
+            | Self::ExpectedVariableName { token }
+            | Self::ExpectedDotAfterSuper { token }
+            | Self::TooManyParameters { token }
+            | Self::TooManyArguments { token }
+            | Self::ExpectedColon { token }
+            | Self::ExpectedSemicolonAfterValue { token }
+            | Self::ExpectedSemicolonAfterVariableDeclaration { token }
+            | Self::ExpectedSemicolonAfterReturnValue { token }
+            | Self::ExpectedSemicolonAfterLoopCondition { token }
+            | Self::ExpectedSemicolonAfterBreak { token }
+            | Self::ExpectedSemicolonAfterContinue { token }
+            | Self::ExpectedLeftParenAfterWhile { token }
+            | Self::ExpectedRightParenAfterWhileCondition { token }
+            | Self::ExpectedLeftParenAfterFor { token }
+            | Self::ExpectedRightParenAfterForClauses { token }
+            | Self::ExpectedLeftBraceBeforeClassBody { token }
+            | Self::ExpectedRightBraceAfterClassBody { token }
+            | Self::ExpectedLeftParenAfterFunctionName { token }
+            | Self::ExpectedLeftParenAfterMethodName { token }
+            | Self::ExpectedRightParenAfterParameters { token }
+            | Self::ExpectedLeftBraceBeforeFunctionBody { token }
+            | Self::ExpectedLeftBraceBeforeMethodBody { token }
+            | Self::ExpectedRightBraceAfterBlock { token }
+            | Self::ExpectedRightParenAfterArguments { token }
+            | Self::ExpectedRightParenAfterExpression { token }
+            | Self::ExpectedClassName { token }
+            | Self::ExpectedSuperclassName { token }
+            | Self::ExpectedFunctionName { token }
+            | Self::ExpectedMethodName { token }
+            | Self::ExpectedParameterName { token }
+            | Self::ExpectedPropertyName { token }
+            | Self::ExpectedSuperclassMethodName { token } => token,
+        }
+    }
+
+    /// A stable identifier for the kind of error, used in JSON diagnostics.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::ExpectedExpression { .. } => "E0100",
+            Self::InvalidAssignmentTarget { .. } => "E0101",
+            Self::ExpectedSemicolonAfterExpression { .. } => "E0102",
+            Self::ExpectedLeftParenAfterIf { .. } => "E0103",
+            Self::ExpectedRightParenAfterIfCondition { .. } => "E0104",
+            Self::ExpectedVariableName { .. } => "E0105",
+            Self::ExpectedDotAfterSuper { .. } => "E0106",
+            Self::BreakOutsideLoop { .. } => "E0107",
+            Self::TooManyParameters { .. } => "E0108",
+            Self::TooManyArguments { .. } => "E0109",
+            Self::ExpectedColon { .. } => "E0110",
+            Self::ContinueOutsideLoop { .. } => "E0111",
+            Self::ExpectedSemicolonAfterValue { .. } => "E0112",
+            Self::ExpectedSemicolonAfterVariableDeclaration { .. } => "E0113",
+            Self::ExpectedSemicolonAfterReturnValue { .. } => "E0114",
+            Self::ExpectedSemicolonAfterLoopCondition { .. } => "E0115",
+            Self::ExpectedSemicolonAfterBreak { .. } => "E0116",
+            Self::ExpectedSemicolonAfterContinue { .. } => "E0117",
+            Self::ExpectedLeftParenAfterWhile { .. } => "E0118",
+            Self::ExpectedRightParenAfterWhileCondition { .. } => "E0119",
+            Self::ExpectedLeftParenAfterFor { .. } => "E0120",
+            Self::ExpectedRightParenAfterForClauses { .. } => "E0121",
+            Self::ExpectedLeftBraceBeforeClassBody { .. } => "E0122",
+            Self::ExpectedRightBraceAfterClassBody { .. } => "E0123",
+            Self::ExpectedLeftParenAfterFunctionName { .. } => "E0124",
+            Self::ExpectedLeftParenAfterMethodName { .. } => "E0125",
+            Self::ExpectedRightParenAfterParameters { .. } => "E0126",
+            Self::ExpectedLeftBraceBeforeFunctionBody { .. } => "E0127",
+            Self::ExpectedLeftBraceBeforeMethodBody { .. } => "E0128",
+            Self::ExpectedRightBraceAfterBlock { .. } => "E0129",
+            Self::ExpectedRightParenAfterArguments { .. } => "E0130",
+            Self::ExpectedRightParenAfterExpression { .. } => "E0131",
+            Self::ExpectedClassName { .. } => "E0132",
+            Self::ExpectedSuperclassName { .. } => "E0133",
+            Self::ExpectedFunctionName { .. } => "E0134",
+            Self::ExpectedMethodName { .. } => "E0135",
+            Self::ExpectedParameterName { .. } => "E0136",
+            Self::ExpectedPropertyName { .. } => "E0137",
+            Self::ExpectedSuperclassMethodName { .. } => "E0138",
+        }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::error(self.code(), &self.to_string(), self.span());
+        match self {
+            Self::InvalidAssignmentTarget { equals, .. } => diagnostic
+                .with_label("can't assign to this")
+                .with_secondary(equals.span, "assignment happens here"),
+            _ => {
+                let token = self.token();
+                if token.ttype == TokenType::Eof {
+                    diagnostic.with_label("at end")
+                } else {
+                    diagnostic.with_label(&format!("at '{}'", token.lexeme))
+                }
+            }
+        }
+    }
+}
+
+/// Whether [`Parser::function`] is parsing a function declaration or a
+/// method, which only changes the errors it reports.
+#[derive(Clone, Copy)]
+enum FunctionKind {
+    Function,
+    Method,
+}
+
+impl FunctionKind {
+    fn expected_name(self) -> fn(Token) -> ParserError {
+        match self {
+            Self::Function => |token| ParserError::ExpectedFunctionName { token },
+            Self::Method => |token| ParserError::ExpectedMethodName { token },
+        }
+    }
+
+    fn expected_left_paren(self) -> fn(Token) -> ParserError {
+        match self {
+            Self::Function => |token| ParserError::ExpectedLeftParenAfterFunctionName { token },
+            Self::Method => |token| ParserError::ExpectedLeftParenAfterMethodName { token },
+        }
+    }
+
+    fn expected_left_brace(self) -> fn(Token) -> ParserError {
+        match self {
+            Self::Function => |token| ParserError::ExpectedLeftBraceBeforeFunctionBody { token },
+            Self::Method => |token| ParserError::ExpectedLeftBraceBeforeMethodBody { token },
+        }
+    }
 }
 
 pub struct Parser<'a> {
     tokens: &'a [Token],
     current: usize,
+    loop_depth: usize,
+    errors: Vec<ParserError>,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(tokens: &'a [Token]) -> Self {
-        Self { tokens, current: 0 }
+        Self {
+            tokens,
+            current: 0,
+            loop_depth: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Parses the whole program. Syntax errors don't stop the parser: it
+    /// skips to the next statement and carries on, so every error in the
+    /// source is reported at once.
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<ParserError>> {
+        let mut statements = Vec::new();
+        while !self.is_at_end() {
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
+        }
+
+        if self.errors.is_empty() {
+            return Ok(statements);
+        }
+        Err(std::mem::take(&mut self.errors))
+    }
+
+    /// Parses a declaration, recording the error and synchronizing to the
+    /// next statement if it is malformed.
+    fn declaration(&mut self) -> Option<Stmt> {
+        match self.try_declaration() {
+            Ok(statement) => Some(statement),
+            Err(e) => {
+                self.errors.push(e);
+                self.synchronize();
+                None
+            }
+        }
+    }
+
+    fn try_declaration(&mut self) -> Result<Stmt, ParserError> {
+        if self.matches(&[TokenType::Class]) {
+            return self.class_declaration();
+        }
+        if self.matches(&[TokenType::Fun]) {
+            return Ok(Stmt::Function(
+                self.function(FunctionKind::Function)?.into(),
+            ));
+        }
+        if self.matches(&[TokenType::Var]) {
+            return self.var_declaration();
+        }
+
+        self.statement()
+    }
+
+    fn class_declaration(&mut self) -> Result<Stmt, ParserError> {
+        let name = self.consume_identifier(|token| ParserError::ExpectedClassName { token })?;
+
+        let superclass = if self.matches(&[TokenType::Less]) {
+            Some(VariableData {
+                name: self
+                    .consume_identifier(|token| ParserError::ExpectedSuperclassName { token })?,
+                depth: ScopeDepth::default(),
+            })
+        } else {
+            None
+        };
+
+        self.consume(TokenType::LeftBrace, |token| {
+            ParserError::ExpectedLeftBraceBeforeClassBody { token }
+        })?;
+
+        let mut methods = Vec::new();
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            methods.push(self.function(FunctionKind::Method)?.into());
+        }
+
+        self.consume(TokenType::RightBrace, |token| {
+            ParserError::ExpectedRightBraceAfterClassBody { token }
+        })?;
+        Ok(Stmt::Class(ClassData {
+            name,
+            superclass,
+            methods,
+        }))
+    }
+
+    fn function(&mut self, kind: FunctionKind) -> Result<FunctionData, ParserError> {
+        let name = self.consume_identifier(kind.expected_name())?;
+        self.consume(TokenType::LeftParen, kind.expected_left_paren())?;
+
+        let mut params = Vec::new();
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if params.len() >= 255 {
+                    self.error(ParserError::TooManyParameters { token: self.peek() });
+                }
+
+                params.push(
+                    self.consume_identifier(|token| ParserError::ExpectedParameterName { token })?,
+                );
+                if !self.matches(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, |token| {
+            ParserError::ExpectedRightParenAfterParameters { token }
+        })?;
+
+        self.consume(TokenType::LeftBrace, kind.expected_left_brace())?;
+
+        // `break` and `continue` can't reach a loop outside of the function.
+        let enclosing_loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        let body = self.block();
+        self.loop_depth = enclosing_loop_depth;
+
+        Ok(FunctionData {
+            name,
+            params,
+            body: body?,
+        })
+    }
+
+    fn var_declaration(&mut self) -> Result<Stmt, ParserError> {
+        let name = self.consume_identifier(|token| ParserError::ExpectedVariableName { token })?;
+
+        let initializer = if self.matches(&[TokenType::Equal]) {
+            Some(self.expression()?.into())
+        } else {
+            None
+        };
+
+        self.consume(TokenType::Semicolon, |token| {
+            ParserError::ExpectedSemicolonAfterVariableDeclaration { token }
+        })?;
+        Ok(Stmt::Var(VarData { name, initializer }))
+    }
+
+    fn statement(&mut self) -> Result<Stmt, ParserError> {
+        if self.matches(&[TokenType::Break]) {
+            return self.break_statement();
+        }
+        if self.matches(&[TokenType::Continue]) {
+            return self.continue_statement();
+        }
+        if self.matches(&[TokenType::For]) {
+            return self.for_statement();
+        }
+        if self.matches(&[TokenType::If]) {
+            return self.if_statement();
+        }
+        if self.matches(&[TokenType::Print]) {
+            return self.print_statement();
+        }
+        if self.matches(&[TokenType::Return]) {
+            return self.return_statement();
+        }
+        if self.matches(&[TokenType::While]) {
+            return self.while_statement();
+        }
+        if self.matches(&[TokenType::LeftBrace]) {
+            return Ok(Stmt::Block(BlockData {
+                statements: self.block()?,
+            }));
+        }
+
+        self.expression_statement()
+    }
+
+    fn break_statement(&mut self) -> Result<Stmt, ParserError> {
+        let keyword = self.previous();
+        if self.loop_depth == 0 {
+            self.error(ParserError::BreakOutsideLoop {
+                keyword: keyword.clone(),
+            });
+        }
+
+        self.consume(TokenType::Semicolon, |token| {
+            ParserError::ExpectedSemicolonAfterBreak { token }
+        })?;
+        Ok(Stmt::Break(BreakData { keyword }))
+    }
+
+    fn continue_statement(&mut self) -> Result<Stmt, ParserError> {
+        let keyword = self.previous();
+        if self.loop_depth == 0 {
+            self.error(ParserError::ContinueOutsideLoop {
+                keyword: keyword.clone(),
+            });
+        }
+
+        self.consume(TokenType::Semicolon, |token| {
+            ParserError::ExpectedSemicolonAfterContinue { token }
+        })?;
+        Ok(Stmt::Continue(ContinueData { keyword }))
+    }
+
+    /// Parses a `for` loop and desugars it into a `while` loop wrapped in a
+    /// block that holds the initializer.
+    fn for_statement(&mut self) -> Result<Stmt, ParserError> {
+        self.consume(TokenType::LeftParen, |token| {
+            ParserError::ExpectedLeftParenAfterFor { token }
+        })?;
+
+        let initializer = if self.matches(&[TokenType::Semicolon]) {
+            None
+        } else if self.matches(&[TokenType::Var]) {
+            Some(self.var_declaration()?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let condition = if !self.check(&TokenType::Semicolon) {
+            self.expression()?
+        } else {
+            Expr::Literal(LiteralData {
+                value: Literal::Bool(true),
+                span: self.peek().span,
+            })
+        };
+        self.consume(TokenType::Semicolon, |token| {
+            ParserError::ExpectedSemicolonAfterLoopCondition { token }
+        })?;
+
+        let increment = if !self.check(&TokenType::RightParen) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+        self.consume(TokenType::RightParen, |token| {
+            ParserError::ExpectedRightParenAfterForClauses { token }
+        })?;
+
+        let body = self.loop_body()?;
+
+        let mut body = Stmt::While(WhileData {
+            condition: condition.into(),
+            body: body.into(),
+            increment: increment.map(Rc::new),
+        });
+
+        if let Some(initializer) = initializer {
+            body = Stmt::Block(BlockData {
+                statements: vec![initializer, body],
+            });
+        }
+
+        Ok(body)
+    }
+
+    fn if_statement(&mut self) -> Result<Stmt, ParserError> {
+        self.consume(TokenType::LeftParen, |token| {
+            ParserError::ExpectedLeftParenAfterIf { token }
+        })?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, |token| {
+            ParserError::ExpectedRightParenAfterIfCondition { token }
+        })?;
+
+        let then_branch = self.statement()?;
+        let else_branch = if self.matches(&[TokenType::Else]) {
+            Some(self.statement()?.into())
+        } else {
+            None
+        };
+
+        Ok(Stmt::If(IfData {
+            condition: condition.into(),
+            then_branch: then_branch.into(),
+            else_branch,
+        }))
     }
 
-    pub fn parse(&mut self) -> Result<Expr, ParserError> {
-        self.expression()
+    fn while_statement(&mut self) -> Result<Stmt, ParserError> {
+        self.consume(TokenType::LeftParen, |token| {
+            ParserError::ExpectedLeftParenAfterWhile { token }
+        })?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, |token| {
+            ParserError::ExpectedRightParenAfterWhileCondition { token }
+        })?;
+        let body = self.loop_body()?;
+
+        Ok(Stmt::While(WhileData {
+            condition: condition.into(),
+            body: body.into(),
+            increment: None,
+        }))
+    }
+
+    fn loop_body(&mut self) -> Result<Stmt, ParserError> {
+        self.loop_depth += 1;
+        let body = self.statement();
+        self.loop_depth -= 1;
+        body
+    }
+
+    fn print_statement(&mut self) -> Result<Stmt, ParserError> {
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, |token| {
+            ParserError::ExpectedSemicolonAfterValue { token }
+        })?;
+        Ok(Stmt::Print(PrintData {
+            expression: value.into(),
+        }))
+    }
+
+    fn return_statement(&mut self) -> Result<Stmt, ParserError> {
+        let keyword = self.previous();
+        let value = if !self.check(&TokenType::Semicolon) {
+            Some(self.expression()?.into())
+        } else {
+            None
+        };
+
+        self.consume(TokenType::Semicolon, |token| {
+            ParserError::ExpectedSemicolonAfterReturnValue { token }
+        })?;
+        Ok(Stmt::Return(ReturnData { keyword, value }))
+    }
+
+    fn expression_statement(&mut self) -> Result<Stmt, ParserError> {
+        let expr = self.expression()?;
+        self.consume(TokenType::Semicolon, |token| {
+            ParserError::ExpectedSemicolonAfterExpression { token }
+        })?;
+        Ok(Stmt::Expression(ExpressionData {
+            expression: expr.into(),
+        }))
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, ParserError> {
+        let mut statements = Vec::new();
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
+        }
+
+        self.consume(TokenType::RightBrace, |token| {
+            ParserError::ExpectedRightBraceAfterBlock { token }
+        })?;
+        Ok(statements)
     }
 
     fn expression(&mut self) -> Result<Expr, ParserError> {
-        self.equality()
+        self.comma()
+    }
+
+    fn comma(&mut self) -> Result<Expr, ParserError> {
+        let mut ex = self.assignment()?;
+
+        while self.matches(&[TokenType::Comma]) {
+            let op = self.previous();
+            let right = self.assignment()?;
+            let data = BinaryData {
+                left: ex.into(),
+                operator: op,
+                right: right.into(),
+            };
+            ex = Expr::Binary(data);
+        }
+
+        Ok(ex)
+    }
+
+    fn assignment(&mut self) -> Result<Expr, ParserError> {
+        let ex = self.conditional()?;
+
+        if self.matches(&[TokenType::Equal]) {
+            let equals = self.previous();
+            let value = self.assignment()?;
+
+            let target = ex.span();
+            match ex {
+                Expr::Variable(data) => {
+                    return Ok(Expr::Assign(AssignData {
+                        name: data.name,
+                        value: value.into(),
+                        depth: ScopeDepth::default(),
+                    }));
+                }
+                Expr::Get(data) => {
+                    return Ok(Expr::Set(SetData {
+                        object: data.object,
+                        name: data.name,
+                        value: value.into(),
+                    }));
+                }
+                ex => {
+                    self.error(ParserError::InvalidAssignmentTarget { equals, target });
+                    return Ok(ex);
+                }
+            }
+        }
+
+        Ok(ex)
+    }
+
+    fn conditional(&mut self) -> Result<Expr, ParserError> {
+        let ex = self.or()?;
+
+        if self.matches(&[TokenType::Question]) {
+            let then_branch = self.expression()?;
+            if !self.matches(&[TokenType::Colon]) {
+                return Err(ParserError::ExpectedColon { token: self.peek() });
+            }
+            let else_branch = self.conditional()?;
+
+            return Ok(Expr::Conditional(ConditionalData {
+                condition: ex.into(),
+                then_branch: then_branch.into(),
+                else_branch: else_branch.into(),
+            }));
+        }
+
+        Ok(ex)
+    }
+
+    fn or(&mut self) -> Result<Expr, ParserError> {
+        let mut ex = self.and()?;
+
+        while self.matches(&[TokenType::Or]) {
+            let op = self.previous();
+            let right = self.and()?;
+            let data = LogicalData {
+                left: ex.into(),
+                operator: op,
+                right: right.into(),
+            };
+            ex = Expr::Logical(data);
+        }
+
+        Ok(ex)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParserError> {
+        let mut ex = self.equality()?;
+
+        while self.matches(&[TokenType::And]) {
+            let op = self.previous();
+            let right = self.equality()?;
+            let data = LogicalData {
+                left: ex.into(),
+                operator: op,
+                right: right.into(),
+            };
+            ex = Expr::Logical(data);
+        }
+
+        Ok(ex)
     }
 
     fn equality(&mut self) -> Result<Expr, ParserError> {
@@ -40,7 +753,7 @@ impl<'a> Parser<'a> {
             ex = Expr::Binary(data);
         }
 
-        return Ok(ex);
+        Ok(ex)
     }
 
     fn comparison(&mut self) -> Result<Expr, ParserError> {
@@ -58,7 +771,7 @@ impl<'a> Parser<'a> {
             ex = Expr::Binary(data);
         }
 
-        return Ok(ex);
+        Ok(ex)
     }
 
     fn term(&mut self) -> Result<Expr, ParserError> {
@@ -76,7 +789,7 @@ impl<'a> Parser<'a> {
             ex = Expr::Binary(data);
         }
 
-        return Ok(ex);
+        Ok(ex)
     }
 
     fn factor(&mut self) -> Result<Expr, ParserError> {
@@ -94,7 +807,7 @@ impl<'a> Parser<'a> {
             ex = Expr::Binary(data);
         }
 
-        return Ok(ex);
+        Ok(ex)
     }
 
     fn unary(&mut self) -> Result<Expr, ParserError> {
@@ -110,48 +823,119 @@ impl<'a> Parser<'a> {
             return Ok(Expr::Unary(data));
         }
 
-        return self.primary();
+        self.call()
+    }
+
+    fn call(&mut self) -> Result<Expr, ParserError> {
+        let mut ex = self.primary()?;
+
+        loop {
+            if self.matches(&[TokenType::LeftParen]) {
+                ex = self.finish_call(ex)?;
+            } else if self.matches(&[TokenType::Dot]) {
+                let name =
+                    self.consume_identifier(|token| ParserError::ExpectedPropertyName { token })?;
+                ex = Expr::Get(GetData {
+                    object: ex.into(),
+                    name,
+                });
+            } else {
+                break;
+            }
+        }
+
+        Ok(ex)
+    }
+
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, ParserError> {
+        let mut arguments = Vec::new();
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if arguments.len() >= 255 {
+                    self.error(ParserError::TooManyArguments { token: self.peek() });
+                }
+
+                // Arguments are separated by commas, so they can't be comma
+                // expressions themselves.
+                arguments.push(self.assignment()?);
+                if !self.matches(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
+        let paren = self.consume(TokenType::RightParen, |token| {
+            ParserError::ExpectedRightParenAfterArguments { token }
+        })?;
+
+        Ok(Expr::Call(CallData {
+            callee: callee.into(),
+            paren,
+            arguments,
+        }))
     }
 
     fn primary(&mut self) -> Result<Expr, ParserError> {
         use TokenType::*;
         match self.peek().ttype {
-            Number(n) => {
-                self.advance();
-                Ok(Expr::Literal(Literal::Number(n)))
-            }
-            String(s) => {
-                self.advance();
-                Ok(Expr::Literal(Literal::String(s)))
-            }
-            True => {
-                self.advance();
-                Ok(Expr::Literal(Literal::Bool(true)))
-            }
-            False => {
-                self.advance();
-                Ok(Expr::Literal(Literal::Bool(false)))
-            }
-            Nil => {
-                self.advance();
-                Ok(Expr::Literal(Literal::Nil))
+            Number(n) => Ok(self.literal(Literal::Number(n))),
+            String(s) => Ok(self.literal(Literal::String(s))),
+            True => Ok(self.literal(Literal::Bool(true))),
+            False => Ok(self.literal(Literal::Bool(false))),
+            Nil => Ok(self.literal(Literal::Nil)),
+            Super => {
+                let keyword = self.advance();
+                if !self.matches(&[Dot]) {
+                    return Err(ParserError::ExpectedDotAfterSuper { token: self.peek() });
+                }
+                let method = self.consume_identifier(|token| {
+                    ParserError::ExpectedSuperclassMethodName { token }
+                })?;
+                Ok(Expr::Super(SuperData {
+                    keyword,
+                    method,
+                    depth: ScopeDepth::default(),
+                }))
             }
+            This => Ok(Expr::This(ThisData {
+                keyword: self.advance(),
+                depth: ScopeDepth::default(),
+            })),
+            Identifier(_) => Ok(Expr::Variable(VariableData {
+                name: self.advance(),
+                depth: ScopeDepth::default(),
+            })),
             LeftParen => {
-                self.advance();
+                let left_paren = self.advance();
                 let e = self.expression()?;
+                let right_paren = self.consume(RightParen, |token| {
+                    ParserError::ExpectedRightParenAfterExpression { token }
+                })?;
                 let data = GroupingData {
                     expression: e.into(),
+                    span: left_paren.span.to(right_paren.span),
                 };
-                self.consume(RightParen, "Expect ')' after expression.")?;
-                return Ok(Expr::Grouping(data));
+                Ok(Expr::Grouping(data))
             }
-            _ => Err(ParserError::Custom {
-                message: "Expect expression.".to_string(),
-                token: self.peek(),
-            }),
+            _ => Err(ParserError::ExpectedExpression { token: self.peek() }),
         }
     }
 
+    /// Consumes the current token as a literal holding `value`.
+    fn literal(&mut self, value: Literal) -> Expr {
+        let token = self.advance();
+        Expr::Literal(LiteralData {
+            value,
+            span: token.span,
+        })
+    }
+
+    /// Records an error that doesn't leave the parser confused about where it
+    /// is, so parsing can continue without synchronizing.
+    fn error(&mut self, error: ParserError) {
+        self.errors.push(error);
+    }
+
     fn matches(&mut self, values: &[TokenType]) -> bool {
         for t in values {
             if self.check(t) {
@@ -160,18 +944,30 @@ impl<'a> Parser<'a> {
             }
         }
 
-        return false;
+        false
     }
 
-    fn consume(&mut self, ttype: TokenType, message: &str) -> Result<Token, ParserError> {
+    fn consume(
+        &mut self,
+        ttype: TokenType,
+        error: fn(Token) -> ParserError,
+    ) -> Result<Token, ParserError> {
         if self.check(&ttype) {
             return Ok(self.advance());
         }
 
-        return Err(ParserError::Custom {
-            message: message.into(),
-            token: self.peek().clone(),
-        });
+        Err(error(self.peek()))
+    }
+
+    fn consume_identifier(
+        &mut self,
+        error: fn(Token) -> ParserError,
+    ) -> Result<Token, ParserError> {
+        if let TokenType::Identifier(_) = self.peek().ttype {
+            return Ok(self.advance());
+        }
+
+        Err(error(self.peek()))
     }
 
     fn check(&self, ttype: &TokenType) -> bool {
@@ -179,26 +975,26 @@ impl<'a> Parser<'a> {
             return false;
         }
 
-        return self.peek().ttype == *ttype;
+        self.peek().ttype == *ttype
     }
 
     fn advance(&mut self) -> Token {
         if !self.is_at_end() {
             self.current += 1;
         }
-        return self.previous();
+        self.previous()
     }
 
     fn is_at_end(&self) -> bool {
-        return self.peek().ttype == TokenType::Eof;
+        self.peek().ttype == TokenType::Eof
     }
 
     fn peek(&self) -> Token {
-        return self.tokens[self.current].clone();
+        self.tokens[self.current].clone()
     }
 
     fn previous(&self) -> Token {
-        return self.tokens[self.current - 1].clone();
+        self.tokens[self.current - 1].clone()
     }
 
     fn synchronize(&mut self) {
@@ -216,3 +1012,108 @@ impl<'a> Parser<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::scanner::Scanner;
+
+    fn errors(source: &str) -> Vec<ParserError> {
+        let tokens = Scanner::new(source.as_bytes()).scan_tokens().unwrap();
+        match Parser::new(&tokens).parse() {
+            Ok(_) => panic!("{source}: expected a syntax error"),
+            Err(errors) => errors,
+        }
+    }
+
+    fn first_error(source: &str) -> ParserError {
+        errors(source).into_iter().next().unwrap()
+    }
+
+    #[test]
+    fn every_message_has_its_own_code() {
+        let params = vec!["p"; 256].join(", ");
+        let too_many_parameters = format!("fun f({params}) {{}}");
+        let too_many_arguments = format!("f({params});");
+        let sources = [
+            "print;",
+            "1 = 2;",
+            "1",
+            "if 1) {}",
+            "if (1 {}",
+            "var = 1;",
+            "super;",
+            "break;",
+            "fun f(a) {} f(1 ? 2 3);",
+            "continue;",
+            "print 1",
+            "var a = 1",
+            "fun f() { return 1 }",
+            "for (;1) {}",
+            "while (true) { break }",
+            "while (true) { continue }",
+            "while true) {}",
+            "while (true {}",
+            "for ;;) {}",
+            "for (;; 1 {}",
+            "class A }",
+            "class A { m() {}",
+            "fun f {}",
+            "class A { m {} }",
+            "fun f(a {}",
+            "fun f() print 1;",
+            "class A { m() print 1; }",
+            "{ print 1;",
+            "f(1;",
+            "(1;",
+            "class {}",
+            "class A < {}",
+            "fun () {}",
+            "class A { () {} }",
+            "fun f(1) {}",
+            "a.1;",
+            "super.1;",
+            &too_many_parameters,
+            &too_many_arguments,
+        ];
+        let mut codes: HashMap<&str, String> = HashMap::new();
+        for source in sources {
+            let error = first_error(source);
+            let message = error.to_string();
+            if let Some(other) = codes.insert(error.code(), message.clone()) {
+                assert_eq!(other, message, "{source}: {} is shared", error.code());
+            }
+        }
+        assert_eq!(codes.len(), sources.len());
+    }
+
+    #[test]
+    fn reports_every_error_and_recovers_at_statement_boundaries() {
+        let source = "
+            var = 1;
+            print 1 +;
+            fun f( { }
+            var ok = 2;
+            print ok
+            var done = 3;
+        ";
+        let errors = errors(source);
+        let found: Vec<_> = errors
+            .iter()
+            .map(|error| (error.code(), error.span().line))
+            .collect();
+        assert_eq!(
+            found,
+            [("E0105", 2), ("E0100", 3), ("E0136", 4), ("E0112", 7)]
+        );
+    }
+
+    #[test]
+    fn keeps_parsing_after_errors_that_need_no_recovery() {
+        let errors = errors("break; 1 = 2; continue; print 1;");
+        let codes: Vec<_> = errors.iter().map(ParserError::code).collect();
+        assert_eq!(codes, ["E0107", "E0101", "E0111"]);
+    }
+}
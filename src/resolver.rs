@@ -0,0 +1,397 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+};
+
+use thiserror::Error;
+
+use crate::{
+    diagnostic::Diagnostic,
+    expr::{Expr, ExpressionVisitor, ScopeDepth, VariableData},
+    literal::Literal,
+    stmt::{FunctionData, StatementVisitor, Stmt},
+    symbol::Symbol,
+    token::{Span, Token},
+};
+
+#[derive(Error, Debug)]
+pub enum ResolverError {
+    #[error("Can't read local variable in its own initializer.")]
+    ReadInOwnInitializer { name: Token },
+
+    #[error("Already a variable with this name in this scope.")]
+    AlreadyDeclared { name: Token },
+
+    #[error("Can't return from top-level code.")]
+    TopLevelReturn { keyword: Token },
+
+    #[error("Can't return a value from an initializer.")]
+    ReturnFromInitializer { keyword: Token },
+
+    #[error("Can't use 'this' outside of a class.")]
+    ThisOutsideClass { keyword: Token },
+
+    #[error("A class can't inherit from itself.")]
+    InheritFromSelf { name: Token },
+
+    #[error("Can't use 'super' outside of a class.")]
+    SuperOutsideClass { keyword: Token },
+
+    #[error("Can't use 'super' in a class with no superclass.")]
+    SuperWithoutSuperclass { keyword: Token },
+}
+
+impl ResolverError {
+    pub fn span(&self) -> Span {
+        self.token().span
+    }
+
+    /// A stable identifier for the kind of error, used in JSON diagnostics.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::ReadInOwnInitializer { .. } => "E0200",
+            Self::AlreadyDeclared { .. } => "E0201",
+            Self::TopLevelReturn { .. } => "E0202",
+            Self::ReturnFromInitializer { .. } => "E0203",
+            Self::ThisOutsideClass { .. } => "E0204",
+            Self::InheritFromSelf { .. } => "E0205",
+            Self::SuperOutsideClass { .. } => "E0206",
+            Self::SuperWithoutSuperclass { .. } => "E0207",
+        }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::error(self.code(), &self.to_string(), self.span())
+    }
+
+    /// The token the error should be reported at.
+    pub fn token(&self) -> &Token {
+        match self {
+            Self::ReadInOwnInitializer { name }
+            | Self::AlreadyDeclared { name }
+            | Self::InheritFromSelf { name } => name,
+            Self::TopLevelReturn { keyword }
+            | Self::ReturnFromInitializer { keyword }
+            | Self::ThisOutsideClass { keyword }
+            | Self::SuperOutsideClass { keyword }
+            | Self::SuperWithoutSuperclass { keyword } => keyword,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+/// Static pass run between parsing and interpreting. It records how many
+/// scopes away each local variable reference was declared and reports
+/// errors that can be found without running the program.
+pub struct Resolver {
+    /// One map per enclosing local scope. A name maps to `false` while its
+    /// initializer is being resolved and to `true` once it is defined.
+    scopes: RefCell<Vec<HashMap<Symbol, bool>>>,
+    current_function: Cell<FunctionType>,
+    current_class: Cell<ClassType>,
+    errors: RefCell<Vec<ResolverError>>,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            scopes: RefCell::new(Vec::new()),
+            current_function: Cell::new(FunctionType::None),
+            current_class: Cell::new(ClassType::None),
+            errors: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn resolve(self, statements: &[Stmt]) -> Result<(), Vec<ResolverError>> {
+        self.resolve_statements(statements);
+
+        let errors = self.errors.into_inner();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn resolve_statements(&self, statements: &[Stmt]) {
+        for statement in statements {
+            statement.accept(self);
+        }
+    }
+
+    fn resolve_expr(&self, expr: &Expr) {
+        expr.accept(self);
+    }
+
+    fn resolve_local(&self, name: &Token, depth: &ScopeDepth) {
+        let scopes = self.scopes.borrow();
+        let distance = scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(&name.lexeme));
+        depth.set(distance);
+    }
+
+    fn resolve_function(&self, function: &FunctionData, kind: FunctionType) {
+        let enclosing_function = self.current_function.replace(kind);
+
+        self.begin_scope();
+        for param in &function.params {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve_statements(&function.body);
+        self.end_scope();
+
+        self.current_function.set(enclosing_function);
+    }
+
+    fn begin_scope(&self) {
+        self.scopes.borrow_mut().push(HashMap::new());
+    }
+
+    fn end_scope(&self) {
+        self.scopes.borrow_mut().pop();
+    }
+
+    fn declare(&self, name: &Token) {
+        let mut scopes = self.scopes.borrow_mut();
+        let Some(scope) = scopes.last_mut() else {
+            return;
+        };
+
+        if scope.contains_key(&name.lexeme) {
+            self.error(ResolverError::AlreadyDeclared { name: name.clone() });
+        }
+        scope.insert(name.lexeme, false);
+    }
+
+    fn define(&self, name: &Token) {
+        if let Some(scope) = self.scopes.borrow_mut().last_mut() {
+            scope.insert(name.lexeme, true);
+        }
+    }
+
+    fn error(&self, error: ResolverError) {
+        self.errors.borrow_mut().push(error);
+    }
+}
+
+impl ExpressionVisitor<()> for Resolver {
+    fn visit_binary(&self, left: &Expr, _operand: &Token, right: &Expr) {
+        self.resolve_expr(left);
+        self.resolve_expr(right);
+    }
+
+    fn visit_unary(&self, _operand: &Token, expr: &Expr) {
+        self.resolve_expr(expr);
+    }
+
+    fn visit_literal(&self, _literal: &Literal) {}
+
+    fn visit_grouping(&self, expr: &Expr) {
+        self.resolve_expr(expr);
+    }
+
+    fn visit_variable(&self, name: &Token, depth: &ScopeDepth) {
+        let in_own_initializer = self
+            .scopes
+            .borrow()
+            .last()
+            .is_some_and(|scope| scope.get(&name.lexeme) == Some(&false));
+        if in_own_initializer {
+            self.error(ResolverError::ReadInOwnInitializer { name: name.clone() });
+        }
+
+        self.resolve_local(name, depth);
+    }
+
+    fn visit_assign(&self, name: &Token, value: &Expr, depth: &ScopeDepth) {
+        self.resolve_expr(value);
+        self.resolve_local(name, depth);
+    }
+
+    fn visit_logical(&self, left: &Expr, _operator: &Token, right: &Expr) {
+        self.resolve_expr(left);
+        self.resolve_expr(right);
+    }
+
+    fn visit_conditional(&self, condition: &Expr, then_branch: &Expr, else_branch: &Expr) {
+        self.resolve_expr(condition);
+        self.resolve_expr(then_branch);
+        self.resolve_expr(else_branch);
+    }
+
+    fn visit_call(&self, callee: &Expr, _paren: &Token, arguments: &[Expr]) {
+        self.resolve_expr(callee);
+        for argument in arguments {
+            self.resolve_expr(argument);
+        }
+    }
+
+    fn visit_get(&self, object: &Expr, _name: &Token) {
+        self.resolve_expr(object);
+    }
+
+    fn visit_set(&self, object: &Expr, _name: &Token, value: &Expr) {
+        self.resolve_expr(value);
+        self.resolve_expr(object);
+    }
+
+    fn visit_this(&self, keyword: &Token, depth: &ScopeDepth) {
+        if self.current_class.get() == ClassType::None {
+            self.error(ResolverError::ThisOutsideClass {
+                keyword: keyword.clone(),
+            });
+            return;
+        }
+
+        self.resolve_local(keyword, depth);
+    }
+
+    fn visit_super(&self, keyword: &Token, _method: &Token, depth: &ScopeDepth) {
+        match self.current_class.get() {
+            ClassType::None => self.error(ResolverError::SuperOutsideClass {
+                keyword: keyword.clone(),
+            }),
+            ClassType::Class => self.error(ResolverError::SuperWithoutSuperclass {
+                keyword: keyword.clone(),
+            }),
+            ClassType::Subclass => self.resolve_local(keyword, depth),
+        }
+    }
+}
+
+impl StatementVisitor<()> for Resolver {
+    fn visit_expression(&self, expr: &Expr) {
+        self.resolve_expr(expr);
+    }
+
+    fn visit_print(&self, expr: &Expr) {
+        self.resolve_expr(expr);
+    }
+
+    fn visit_var(&self, name: &Token, initializer: Option<&Expr>) {
+        self.declare(name);
+        if let Some(initializer) = initializer {
+            self.resolve_expr(initializer);
+        }
+        self.define(name);
+    }
+
+    fn visit_block(&self, statements: &[Stmt]) {
+        self.begin_scope();
+        self.resolve_statements(statements);
+        self.end_scope();
+    }
+
+    fn visit_if(&self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>) {
+        self.resolve_expr(condition);
+        then_branch.accept(self);
+        if let Some(else_branch) = else_branch {
+            else_branch.accept(self);
+        }
+    }
+
+    fn visit_while(&self, condition: &Expr, body: &Stmt, increment: Option<&Expr>) {
+        self.resolve_expr(condition);
+        body.accept(self);
+        if let Some(increment) = increment {
+            self.resolve_expr(increment);
+        }
+    }
+
+    fn visit_break(&self, _keyword: &Token) {}
+
+    fn visit_continue(&self, _keyword: &Token) {}
+
+    fn visit_function(&self, declaration: &Rc<FunctionData>) {
+        // Define the name eagerly so the function can refer to itself.
+        self.declare(&declaration.name);
+        self.define(&declaration.name);
+
+        self.resolve_function(declaration, FunctionType::Function);
+    }
+
+    fn visit_return(&self, keyword: &Token, value: Option<&Expr>) {
+        if self.current_function.get() == FunctionType::None {
+            self.error(ResolverError::TopLevelReturn {
+                keyword: keyword.clone(),
+            });
+        }
+
+        if let Some(value) = value {
+            if self.current_function.get() == FunctionType::Initializer {
+                self.error(ResolverError::ReturnFromInitializer {
+                    keyword: keyword.clone(),
+                });
+            }
+
+            self.resolve_expr(value);
+        }
+    }
+
+    fn visit_class(
+        &self,
+        name: &Token,
+        superclass: Option<&VariableData>,
+        methods: &[Rc<FunctionData>],
+    ) {
+        let enclosing_class = self.current_class.replace(ClassType::Class);
+
+        self.declare(name);
+        self.define(name);
+
+        if let Some(superclass) = superclass {
+            if superclass.name.lexeme == name.lexeme {
+                self.error(ResolverError::InheritFromSelf {
+                    name: superclass.name.clone(),
+                });
+            }
+
+            self.current_class.set(ClassType::Subclass);
+            self.visit_variable(&superclass.name, &superclass.depth);
+
+            self.begin_scope();
+            if let Some(scope) = self.scopes.borrow_mut().last_mut() {
+                scope.insert(Symbol::intern("super"), true);
+            }
+        }
+
+        self.begin_scope();
+        if let Some(scope) = self.scopes.borrow_mut().last_mut() {
+            scope.insert(Symbol::intern("this"), true);
+        }
+
+        for method in methods {
+            let kind = if method.name.lexeme.as_str() == "init" {
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+            self.resolve_function(method, kind);
+        }
+
+        self.end_scope();
+        if superclass.is_some() {
+            self.end_scope();
+        }
+
+        self.current_class.set(enclosing_class);
+    }
+}
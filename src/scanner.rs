@@ -2,24 +2,70 @@ use std::collections::HashMap;
 use std::str::FromStr;
 use thiserror::Error;
 
-use crate::token::{Token, TokenType};
+use crate::{
+    diagnostic::Diagnostic,
+    symbol::Symbol,
+    token::{Span, Token, TokenType},
+};
 
 
 // Source for this error type:
 // https://github.com/abesto/jlox-rs/blob/main/src/scanner.rs
 #[derive(Error, Debug)]
 pub enum ScannerError {
-    #[error("Invalid UTF-8 character at {line}")]
-    InvalidUtf8Char { line: usize },
+    #[error("Invalid UTF-8 character.")]
+    InvalidUtf8Char { span: Span },
 
-    #[error("Unexpected character `{c}` at {line}")]
-    UnexpectedCharacter { c: char, line: usize },
+    #[error("Unexpected character `{c}`.")]
+    UnexpectedCharacter { c: char, span: Span },
 
-    #[error("Unterminated string starting at {line}")]
-    UnterminatedString { line: usize },
+    #[error("Unterminated string.")]
+    UnterminatedString { span: Span },
 
-    #[error("Unterminated /* block comment */ starting at {line}")]
-    UnterminatedComment { line: usize },
+    #[error("Unterminated /* block comment */.")]
+    UnterminatedComment { span: Span },
+
+    #[error("Invalid escape sequence `{sequence}`.")]
+    InvalidEscape { sequence: String, span: Span },
+}
+
+impl ScannerError {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::InvalidUtf8Char { span }
+            | Self::UnexpectedCharacter { span, .. }
+            | Self::UnterminatedString { span }
+            | Self::UnterminatedComment { span }
+            | Self::InvalidEscape { span, .. } => *span,
+        }
+    }
+
+    /// A stable identifier for the kind of error, used in JSON diagnostics.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidUtf8Char { .. } => "E0001",
+            Self::UnexpectedCharacter { .. } => "E0002",
+            Self::UnterminatedString { .. } => "E0003",
+            Self::UnterminatedComment { .. } => "E0004",
+            Self::InvalidEscape { .. } => "E0005",
+        }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::error(self.code(), &self.to_string(), self.span());
+        match self {
+            Self::UnterminatedString { .. } => {
+                diagnostic.with_label("string starts here but is never closed")
+            }
+            Self::UnterminatedComment { .. } => diagnostic
+                .with_label("comment starts here but is never closed")
+                .with_note("block comments nest, so every `/*` needs its own `*/`"),
+            Self::InvalidEscape { .. } => diagnostic.with_note(
+                "valid escapes are \\\", \\\\, \\n, \\t, \\r, \\0 and \\u{XXXX}",
+            ),
+            _ => diagnostic,
+        }
+    }
 }
 
 pub struct Scanner<'a> {
@@ -27,7 +73,8 @@ pub struct Scanner<'a> {
     start: usize,
     current: usize,
     line: usize,
-    keywords: HashMap<&'a str, TokenType>,
+    start_line: usize,
+    keywords: HashMap<Symbol, TokenType>,
 }
 
 impl<'a> Scanner<'a> {
@@ -37,23 +84,26 @@ impl<'a> Scanner<'a> {
             start: 0,
             current: 0,
             line: 1,
+            start_line: 1,
             keywords: HashMap::from([
-                ("and", TokenType::And),
-                ("class", TokenType::Class),
-                ("else", TokenType::Else),
-                ("false", TokenType::False),
-                ("for", TokenType::For),
-                ("fun", TokenType::Fun),
-                ("if", TokenType::If),
-                ("nil", TokenType::Nil),
-                ("or", TokenType::Or),
-                ("print", TokenType::Print),
-                ("return", TokenType::Return),
-                ("super", TokenType::Super),
-                ("this", TokenType::This),
-                ("true", TokenType::True),
-                ("var", TokenType::Var),
-                ("while", TokenType::While),
+                (Symbol::intern("and"), TokenType::And),
+                (Symbol::intern("break"), TokenType::Break),
+                (Symbol::intern("class"), TokenType::Class),
+                (Symbol::intern("continue"), TokenType::Continue),
+                (Symbol::intern("else"), TokenType::Else),
+                (Symbol::intern("false"), TokenType::False),
+                (Symbol::intern("for"), TokenType::For),
+                (Symbol::intern("fun"), TokenType::Fun),
+                (Symbol::intern("if"), TokenType::If),
+                (Symbol::intern("nil"), TokenType::Nil),
+                (Symbol::intern("or"), TokenType::Or),
+                (Symbol::intern("print"), TokenType::Print),
+                (Symbol::intern("return"), TokenType::Return),
+                (Symbol::intern("super"), TokenType::Super),
+                (Symbol::intern("this"), TokenType::This),
+                (Symbol::intern("true"), TokenType::True),
+                (Symbol::intern("var"), TokenType::Var),
+                (Symbol::intern("while"), TokenType::While),
             ]),
         }
     }
@@ -63,6 +113,7 @@ impl<'a> Scanner<'a> {
         let mut errors: Vec<ScannerError> = Vec::new();
         while !self.is_at_end() {
             self.start = self.current;
+            self.start_line = self.line;
             let t = self.scan_token();
             // if let Ok(Some(tok)) = t {
             //     tokens.push(tok);
@@ -73,8 +124,18 @@ impl<'a> Scanner<'a> {
                 Ok(None) => (),
             }
         }
-        tokens.push(Token::new(TokenType::Eof, "", self.line));
-        if errors.len() != 0 {
+        let end = self.source.len();
+        tokens.push(Token::new(
+            TokenType::Eof,
+            "",
+            Span {
+                start: end,
+                end,
+                line: self.line,
+                column: self.column(end),
+            },
+        ));
+        if !errors.is_empty() {
             Err(errors)
         } else {
             Ok(tokens)
@@ -89,6 +150,8 @@ impl<'a> Scanner<'a> {
             b'{' => Ok(Some(self.make_token(TokenType::LeftBrace))),
             b'}' => Ok(Some(self.make_token(TokenType::RightBrace))),
             b',' => Ok(Some(self.make_token(TokenType::Comma))),
+            b':' => Ok(Some(self.make_token(TokenType::Colon))),
+            b'?' => Ok(Some(self.make_token(TokenType::Question))),
             b'.' => Ok(Some(self.make_token(TokenType::Dot))),
             b'-' => Ok(Some(self.make_token(TokenType::Minus))),
             b'+' => Ok(Some(self.make_token(TokenType::Plus))),
@@ -128,6 +191,8 @@ impl<'a> Scanner<'a> {
                         self.advance();
                     }
                     Ok(None)
+                } else if self.matches(b'*') {
+                    self.block_comment()
                 } else {
                     Ok(Some(self.make_token(TokenType::Slash)))
                 }
@@ -142,7 +207,7 @@ impl<'a> Scanner<'a> {
             c if self.is_alpha(c) => self.identifier().map(Some),
             c => Err(ScannerError::UnexpectedCharacter {
                 c: c.into(),
-                line: self.line,
+                span: self.current_span(),
             }),
         }
     }
@@ -161,7 +226,21 @@ impl<'a> Scanner<'a> {
         let text = self
             .substring(self.start, self.current)
             .expect("Compiler error: tried to substring out of bounds");
-        Token::new(ttype, &text, self.line)
+        Token::new(ttype, &text, self.current_span())
+    }
+
+    /// The span of the token being scanned, up to the current position.
+    fn current_span(&self) -> Span {
+        self.span_from(self.start, self.start_line)
+    }
+
+    fn span_from(&self, start: usize, line: usize) -> Span {
+        Span {
+            start,
+            end: self.current,
+            line,
+            column: self.column(start),
+        }
     }
 
     fn matches(&mut self, c: u8) -> bool {
@@ -174,7 +253,7 @@ impl<'a> Scanner<'a> {
         }
 
         self.current += 1;
-        return true;
+        true
     }
 
     fn peek(&self) -> u8 {
@@ -192,31 +271,154 @@ impl<'a> Scanner<'a> {
         }
     }
 
+    /// Skips a `/* ... */` comment whose opening delimiter has already been
+    /// consumed. Block comments nest, so every `/*` needs a matching `*/`.
+    fn block_comment(&mut self) -> Result<Option<Token>, ScannerError> {
+        let mut depth = 1;
+
+        while depth > 0 {
+            if self.is_at_end() {
+                return Err(ScannerError::UnterminatedComment {
+                    span: self.current_span(),
+                });
+            }
+
+            match self.advance() {
+                b'\n' => self.line += 1,
+                b'/' if self.peek() == b'*' => {
+                    self.advance();
+                    depth += 1;
+                }
+                b'*' if self.peek() == b'/' => {
+                    self.advance();
+                    depth -= 1;
+                }
+                _ => (),
+            }
+        }
+
+        Ok(None)
+    }
+
     fn string(&mut self) -> Result<Token, ScannerError> {
+        let mut value: Vec<u8> = Vec::new();
+        let mut error = None;
+
         while self.peek() != b'"' && !self.is_at_end() {
-            if self.peek() == b'\n' {
-                self.line += 1;
+            match self.advance() {
+                b'\\' => {
+                    if let Err(e) = self.escape(&mut value) {
+                        error.get_or_insert(e);
+                    }
+                }
+                c => {
+                    if c == b'\n' {
+                        self.line += 1;
+                    }
+                    value.push(c);
+                }
             }
-            self.advance();
         }
 
         if self.is_at_end() {
-            return Err(ScannerError::UnterminatedString { line: self.line });
+            return Err(ScannerError::UnterminatedString {
+                span: self.current_span(),
+            });
         }
 
         self.advance();
 
-        let value = self.substring(self.start + 1, self.current - 1)?;
-        Ok(Token::new(
-            TokenType::String(value.clone()),
-            &value,
-            self.line,
-        ))
+        // The whole literal is consumed before reporting a bad escape so that
+        // scanning resumes after the closing quote.
+        if let Some(e) = error {
+            return Err(e);
+        }
+
+        let value = String::from_utf8(value).map_err(|_| ScannerError::InvalidUtf8Char {
+            span: self.current_span(),
+        })?;
+        Ok(self.make_token(TokenType::String(Symbol::intern(&value))))
+    }
+
+    /// Decodes the escape sequence following a backslash inside a string
+    /// literal and appends the resulting bytes to `value`.
+    fn escape(&mut self, value: &mut Vec<u8>) -> Result<(), ScannerError> {
+        let start = self.current - 1;
+        let line = self.line;
+
+        if self.is_at_end() {
+            // Reported as an unterminated string by the caller.
+            return Ok(());
+        }
+
+        match self.advance() {
+            b'"' => value.push(b'"'),
+            b'\\' => value.push(b'\\'),
+            b'n' => value.push(b'\n'),
+            b't' => value.push(b'\t'),
+            b'r' => value.push(b'\r'),
+            b'0' => value.push(b'\0'),
+            b'u' => {
+                let c = self.unicode_escape().ok_or_else(|| self.invalid_escape(start, line))?;
+                let mut buffer = [0; 4];
+                value.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+            }
+            c => {
+                let error = self.invalid_escape(start, line);
+                if c == b'\n' {
+                    self.line += 1;
+                }
+                return Err(error);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parses the `{XXXX}` part of a `\u{XXXX}` escape.
+    fn unicode_escape(&mut self) -> Option<char> {
+        if !self.matches(b'{') {
+            return None;
+        }
+
+        let digits_start = self.current;
+        while self.peek().is_ascii_hexdigit() {
+            self.advance();
+        }
+        let digits = self.substring(digits_start, self.current).ok()?;
+
+        if !self.matches(b'}') || digits.is_empty() || digits.len() > 6 {
+            return None;
+        }
+
+        u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32)
+    }
+
+    fn invalid_escape(&self, start: usize, line: usize) -> ScannerError {
+        ScannerError::InvalidEscape {
+            sequence: String::from_utf8_lossy(&self.source[start..self.current]).into_owned(),
+            span: self.span_from(start, line),
+        }
+    }
+
+    /// The 1-based column, counted in characters, of the byte at `offset`.
+    fn column(&self, offset: usize) -> usize {
+        let line_start = self.source[..offset]
+            .iter()
+            .rposition(|&c| c == b'\n')
+            .map_or(0, |i| i + 1);
+        let chars = self.source[line_start..offset]
+            .iter()
+            .filter(|&&c| c & 0xC0 != 0x80)
+            .count();
+        chars + 1
     }
 
     fn substring(&self, start: usize, end: usize) -> Result<String, ScannerError> {
         String::from_utf8(self.source[start..end].to_vec())
-            .map_err(|_| ScannerError::InvalidUtf8Char { line: self.line })
+            .map_err(|_| ScannerError::InvalidUtf8Char {
+                span: self.current_span(),
+            })
     }
 
     fn number(&mut self) -> Result<Token, ScannerError> {
@@ -242,7 +444,7 @@ impl<'a> Scanner<'a> {
         Ok(Token::new(
             TokenType::Number(value),
             &value_string,
-            self.line,
+            self.current_span(),
         ))
     }
 
@@ -262,9 +464,79 @@ impl<'a> Scanner<'a> {
             .substring(self.start, self.current)
             .expect("Compiler error: tried to substring out of bounds");
 
-        match self.keywords.get(&text as &str) {
+        let symbol = Symbol::intern(&text);
+        match self.keywords.get(&symbol) {
             Some(t) => Ok(self.make_token(t.clone())),
-            None => Ok(self.make_token(TokenType::Identifier(text))),
+            None => Ok(self.make_token(TokenType::Identifier(symbol))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(source: &str) -> Result<Vec<Token>, Vec<ScannerError>> {
+        Scanner::new(source.as_bytes()).scan_tokens()
+    }
+
+    fn string(source: &str) -> String {
+        let tokens = scan(source).unwrap_or_else(|errors| panic!("{source}: {errors:?}"));
+        match &tokens[0].ttype {
+            TokenType::String(value) => value.to_string(),
+            ttype => panic!("expected a string, found {ttype:?}"),
+        }
+    }
+
+    #[test]
+    fn decodes_escapes() {
+        assert_eq!(string(r#""a\nb""#), "a\nb");
+        assert_eq!(string(r#""a\tb""#), "a\tb");
+        assert_eq!(string(r#""a\rb""#), "a\rb");
+        assert_eq!(string(r#""a\0b""#), "a\0b");
+        assert_eq!(string(r#""say \"hi\"""#), "say \"hi\"");
+        assert_eq!(string(r#""back\\slash""#), "back\\slash");
+        assert_eq!(string(r#""\u{41}\u{e9}\u{1F600}""#), "Aé😀");
+    }
+
+    #[test]
+    fn reports_invalid_escapes_with_their_position() {
+        let errors = scan("print 1;\nprint \"ok \\q\";").unwrap_err();
+        let [ScannerError::InvalidEscape { sequence, span }] = &errors[..] else {
+            panic!("expected one invalid escape, found {errors:?}");
+        };
+        assert_eq!(sequence, "\\q");
+        assert_eq!((span.line, span.column), (2, 11));
+        assert_eq!(errors[0].to_string(), "Invalid escape sequence `\\q`.");
+    }
+
+    #[test]
+    fn rejects_malformed_unicode_escapes() {
+        for source in [r#""\u41""#, r#""\u{}""#, r#""\u{1234567}""#, r#""\u{D800}""#] {
+            let errors = scan(source).unwrap_err();
+            assert!(
+                matches!(errors[..], [ScannerError::InvalidEscape { .. }]),
+                "{source}: {errors:?}"
+            );
         }
     }
+
+    #[test]
+    fn skips_nested_block_comments() {
+        let tokens = scan("1 /* outer /* inner */ still outer */ 2").unwrap();
+        let types: Vec<_> = tokens.iter().map(|token| &token.ttype).collect();
+        assert!(matches!(
+            types[..],
+            [TokenType::Number(_), TokenType::Number(_), TokenType::Eof]
+        ));
+    }
+
+    #[test]
+    fn reports_unterminated_nested_comments() {
+        let errors = scan("1 /* outer /* inner */ never closed").unwrap_err();
+        let [ScannerError::UnterminatedComment { span }] = &errors[..] else {
+            panic!("expected an unterminated comment, found {errors:?}");
+        };
+        assert_eq!((span.line, span.column), (1, 3));
+    }
 }
@@ -0,0 +1,121 @@
+use std::rc::Rc;
+
+use crate::{
+    expr::{Expr, VariableData},
+    token::Token,
+};
+
+pub trait StatementVisitor<T> {
+    fn visit_expression(&self, expr: &Expr) -> T;
+    fn visit_print(&self, expr: &Expr) -> T;
+    fn visit_var(&self, name: &Token, initializer: Option<&Expr>) -> T;
+    fn visit_block(&self, statements: &[Stmt]) -> T;
+    fn visit_if(&self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>) -> T;
+    fn visit_while(&self, condition: &Expr, body: &Stmt, increment: Option<&Expr>) -> T;
+    fn visit_break(&self, keyword: &Token) -> T;
+    fn visit_continue(&self, keyword: &Token) -> T;
+    fn visit_function(&self, declaration: &Rc<FunctionData>) -> T;
+    fn visit_return(&self, keyword: &Token, value: Option<&Expr>) -> T;
+    fn visit_class(
+        &self,
+        name: &Token,
+        superclass: Option<&VariableData>,
+        methods: &[Rc<FunctionData>],
+    ) -> T;
+}
+
+pub enum Stmt {
+    Expression(ExpressionData),
+    Print(PrintData),
+    Var(VarData),
+    Block(BlockData),
+    If(IfData),
+    While(WhileData),
+    Break(BreakData),
+    Continue(ContinueData),
+    Function(Rc<FunctionData>),
+    Return(ReturnData),
+    Class(ClassData),
+}
+
+impl Stmt {
+    pub fn accept<T, V: StatementVisitor<T>>(&self, visitor: &V) -> T {
+        match self {
+            Stmt::Expression(data) => visitor.visit_expression(&data.expression),
+            Stmt::Print(data) => visitor.visit_print(&data.expression),
+            Stmt::Var(data) => visitor.visit_var(&data.name, data.initializer.as_deref()),
+            Stmt::Block(data) => visitor.visit_block(&data.statements),
+            Stmt::If(data) => visitor.visit_if(
+                &data.condition,
+                &data.then_branch,
+                data.else_branch.as_deref(),
+            ),
+            Stmt::While(data) => {
+                visitor.visit_while(&data.condition, &data.body, data.increment.as_deref())
+            }
+            Stmt::Break(data) => visitor.visit_break(&data.keyword),
+            Stmt::Continue(data) => visitor.visit_continue(&data.keyword),
+            Stmt::Function(data) => visitor.visit_function(data),
+            Stmt::Return(data) => visitor.visit_return(&data.keyword, data.value.as_deref()),
+            Stmt::Class(data) => {
+                visitor.visit_class(&data.name, data.superclass.as_ref(), &data.methods)
+            }
+        }
+    }
+}
+
+pub struct ExpressionData {
+    pub expression: Rc<Expr>,
+}
+
+pub struct PrintData {
+    pub expression: Rc<Expr>,
+}
+
+pub struct VarData {
+    pub name: Token,
+    pub initializer: Option<Rc<Expr>>,
+}
+
+pub struct BlockData {
+    pub statements: Vec<Stmt>,
+}
+
+pub struct IfData {
+    pub condition: Rc<Expr>,
+    pub then_branch: Rc<Stmt>,
+    pub else_branch: Option<Rc<Stmt>>,
+}
+
+pub struct WhileData {
+    pub condition: Rc<Expr>,
+    pub body: Rc<Stmt>,
+    /// The increment clause of a desugared `for` loop. It runs after the body
+    /// on every iteration, including ones cut short by `continue`.
+    pub increment: Option<Rc<Expr>>,
+}
+
+pub struct BreakData {
+    pub keyword: Token,
+}
+
+pub struct ContinueData {
+    pub keyword: Token,
+}
+
+pub struct FunctionData {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Vec<Stmt>,
+}
+
+pub struct ReturnData {
+    pub keyword: Token,
+    pub value: Option<Rc<Expr>>,
+}
+
+pub struct ClassData {
+    pub name: Token,
+    pub superclass: Option<VariableData>,
+    pub methods: Vec<Rc<FunctionData>>,
+}
@@ -0,0 +1,77 @@
+use std::{cell::RefCell, collections::HashMap, fmt, ops::Deref};
+
+/// An interned string. Two symbols are equal exactly when their strings are,
+/// so comparing and hashing them never looks at the text.
+///
+/// Interned strings are never freed; they live in a per-thread table for the
+/// rest of the program.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+#[derive(Default)]
+struct Interner {
+    symbols: HashMap<&'static str, Symbol>,
+    strings: Vec<&'static str>,
+}
+
+thread_local! {
+    static INTERNER: RefCell<Interner> = RefCell::default();
+}
+
+impl Symbol {
+    pub fn intern(string: &str) -> Symbol {
+        INTERNER.with_borrow_mut(|interner| {
+            if let Some(&symbol) = interner.symbols.get(string) {
+                return symbol;
+            }
+            let symbol = Symbol(interner.strings.len() as u32);
+            let string: &'static str = Box::leak(string.into());
+            interner.strings.push(string);
+            interner.symbols.insert(string, symbol);
+            symbol
+        })
+    }
+
+    pub fn as_str(self) -> &'static str {
+        INTERNER.with_borrow(|interner| interner.strings[self.0 as usize])
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_text_interns_to_the_same_symbol() {
+        let text = String::from("counter");
+        assert_eq!(Symbol::intern("counter"), Symbol::intern(&text));
+        assert_eq!(Symbol::intern("counter").as_str(), "counter");
+    }
+
+    #[test]
+    fn different_text_interns_to_different_symbols() {
+        assert_ne!(Symbol::intern("left"), Symbol::intern("right"));
+        assert_ne!(Symbol::intern("a"), Symbol::intern("A"));
+        assert_ne!(Symbol::intern(""), Symbol::intern(" "));
+    }
+}
@@ -1,3 +1,5 @@
+use crate::symbol::Symbol;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
     // Single-character tokens.
@@ -26,14 +28,15 @@ pub enum TokenType {
     LessEqual,
 
     // Literals.
-    Identifier(String),
-    String(String),
+    Identifier(Symbol),
+    String(Symbol),
     Number(f64),
 
     // Keywords.
     And,
     Break,
     Class,
+    Continue,
     Else,
     False,
     Fun,
@@ -52,20 +55,40 @@ pub enum TokenType {
     Eof,
 }
 
+/// A range of source text: the byte offsets `start..end`, plus the 1-based
+/// line and column where it starts.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    /// The smallest span covering both `self` and `other`, assuming `other`
+    /// doesn't start before `self`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: self.end.max(other.end),
+            ..self
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Token {
     pub ttype: TokenType,
-    pub lexeme: String,
-    pub line: usize,
+    pub lexeme: Symbol,
+    pub span: Span,
 }
 
 impl Token {
-    pub fn new(ttype: TokenType, lexeme: &str, line: usize) -> Self {
+    pub fn new(ttype: TokenType, lexeme: &str, span: Span) -> Self {
         Self {
             ttype,
-            lexeme: lexeme.to_string(),
-            line,
+            lexeme: Symbol::intern(lexeme),
+            span,
         }
     }
 }
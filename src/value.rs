@@ -0,0 +1,308 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{chunk::Function, symbol::Symbol};
+
+/// A value on the virtual machine's stack. Strings and natives can't form
+/// cycles, so they are reference counted; everything else lives in the
+/// [`Heap`] and is referred to by handle.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(Rc<str>),
+    Native(Rc<Native>),
+    Object(ObjRef),
+}
+
+impl Value {
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Bool(false))
+    }
+
+    /// The heap object this value refers to, if any.
+    pub fn object(&self) -> Option<ObjRef> {
+        match self {
+            Value::Object(handle) => Some(*handle),
+            _ => None,
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(l), Value::Bool(r)) => l == r,
+            (Value::Number(l), Value::Number(r)) => l == r,
+            (Value::String(l), Value::String(r)) => l == r,
+            (Value::Native(l), Value::Native(r)) => Rc::ptr_eq(l, r),
+            (Value::Object(l), Value::Object(r)) => l == r,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Native {
+    pub arity: usize,
+    pub function: fn(&[Value]) -> Value,
+}
+
+/// A handle to an object in the [`Heap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObjRef(usize);
+
+#[derive(Debug)]
+pub enum HeapObject {
+    Closure(Closure),
+    Upvalue(Upvalue),
+    Class(Class),
+    Instance(Instance),
+    BoundMethod(BoundMethod),
+}
+
+#[derive(Debug)]
+pub struct Closure {
+    pub function: Rc<Function>,
+    pub upvalues: Vec<ObjRef>,
+}
+
+/// A variable captured by a closure. It points at a stack slot while the
+/// variable is still in scope and holds the value itself once it is not.
+#[derive(Debug)]
+pub enum Upvalue {
+    Open(usize),
+    Closed(Value),
+}
+
+#[derive(Debug)]
+pub struct Class {
+    pub name: Symbol,
+    /// Closures for each method, including ones copied from the superclass.
+    pub methods: HashMap<Symbol, ObjRef>,
+}
+
+#[derive(Debug)]
+pub struct Instance {
+    pub class: ObjRef,
+    pub fields: HashMap<Symbol, Value>,
+}
+
+#[derive(Debug)]
+pub struct BoundMethod {
+    pub receiver: Value,
+    pub method: ObjRef,
+}
+
+/// How much the heap grows between collections unless `--gc-growth` says
+/// otherwise.
+pub const DEFAULT_GROWTH_FACTOR: f64 = 2.0;
+
+/// How many objects the heap holds before its first collection.
+const INITIAL_THRESHOLD: usize = 1024;
+
+/// Storage for every object the virtual machine allocates, reclaimed by
+/// mark-and-sweep garbage collection. Slots freed by a collection are reused,
+/// so a handle is only valid while the object is reachable from a root.
+pub struct Heap {
+    objects: Vec<Option<HeapObject>>,
+    /// Indexes of empty slots in `objects`.
+    free: Vec<usize>,
+    live: usize,
+    /// Collect once `live` reaches this many objects.
+    next_gc: usize,
+    /// How much the heap may grow past the objects that survived a
+    /// collection before the next one.
+    growth_factor: f64,
+    /// Collect whenever anything was allocated, to shake out objects that
+    /// aren't reachable from a root.
+    stress: bool,
+    allocated_since_gc: bool,
+}
+
+impl Heap {
+    pub fn new(growth_factor: f64, stress: bool) -> Self {
+        Self {
+            objects: Vec::new(),
+            free: Vec::new(),
+            live: 0,
+            next_gc: INITIAL_THRESHOLD,
+            growth_factor,
+            stress,
+            allocated_since_gc: false,
+        }
+    }
+
+    pub fn alloc(&mut self, object: HeapObject) -> ObjRef {
+        self.live += 1;
+        self.allocated_since_gc = true;
+        match self.free.pop() {
+            Some(index) => {
+                self.objects[index] = Some(object);
+                ObjRef(index)
+            }
+            None => {
+                self.objects.push(Some(object));
+                ObjRef(self.objects.len() - 1)
+            }
+        }
+    }
+
+    pub fn get(&self, handle: ObjRef) -> &HeapObject {
+        self.objects[handle.0]
+            .as_ref()
+            .expect("use of a collected object")
+    }
+
+    pub fn get_mut(&mut self, handle: ObjRef) -> &mut HeapObject {
+        self.objects[handle.0]
+            .as_mut()
+            .expect("use of a collected object")
+    }
+
+    pub fn should_collect(&self) -> bool {
+        if self.stress {
+            return self.allocated_since_gc;
+        }
+        self.live >= self.next_gc
+    }
+
+    /// Frees every object that can't be reached from `roots`.
+    pub fn collect(&mut self, roots: impl IntoIterator<Item = ObjRef>) {
+        let mut marked = vec![false; self.objects.len()];
+        let mut gray: Vec<ObjRef> = roots.into_iter().collect();
+
+        while let Some(handle) = gray.pop() {
+            if std::mem::replace(&mut marked[handle.0], true) {
+                continue;
+            }
+            match self.get(handle) {
+                HeapObject::Closure(closure) => gray.extend(&closure.upvalues),
+                HeapObject::Upvalue(Upvalue::Open(_)) => {}
+                HeapObject::Upvalue(Upvalue::Closed(value)) => gray.extend(value.object()),
+                HeapObject::Class(class) => gray.extend(class.methods.values()),
+                HeapObject::Instance(instance) => {
+                    gray.push(instance.class);
+                    gray.extend(instance.fields.values().filter_map(Value::object));
+                }
+                HeapObject::BoundMethod(bound) => {
+                    gray.push(bound.method);
+                    gray.extend(bound.receiver.object());
+                }
+            }
+        }
+
+        for (index, slot) in self.objects.iter_mut().enumerate() {
+            if slot.is_some() && !marked[index] {
+                *slot = None;
+                self.free.push(index);
+                self.live -= 1;
+            }
+        }
+
+        let next_gc = (self.live as f64 * self.growth_factor) as usize;
+        self.next_gc = next_gc.max(INITIAL_THRESHOLD);
+        self.allocated_since_gc = false;
+    }
+
+    pub fn closure(&self, handle: ObjRef) -> &Closure {
+        match self.get(handle) {
+            HeapObject::Closure(closure) => closure,
+            object => unreachable!("expected a closure, found {object:?}"),
+        }
+    }
+
+    pub fn upvalue_mut(&mut self, handle: ObjRef) -> &mut Upvalue {
+        match self.get_mut(handle) {
+            HeapObject::Upvalue(upvalue) => upvalue,
+            object => unreachable!("expected an upvalue, found {object:?}"),
+        }
+    }
+
+    pub fn class(&self, handle: ObjRef) -> &Class {
+        match self.get(handle) {
+            HeapObject::Class(class) => class,
+            object => unreachable!("expected a class, found {object:?}"),
+        }
+    }
+
+    pub fn class_mut(&mut self, handle: ObjRef) -> &mut Class {
+        match self.get_mut(handle) {
+            HeapObject::Class(class) => class,
+            object => unreachable!("expected a class, found {object:?}"),
+        }
+    }
+
+    /// Formats `value` the same way the tree-walking interpreter prints it.
+    pub fn display(&self, value: &Value) -> String {
+        match value {
+            Value::Nil => "nil".to_string(),
+            Value::Bool(bool) => bool.to_string(),
+            Value::Number(number) => number.to_string(),
+            Value::String(string) => string.to_string(),
+            Value::Native(_) => "<native fn>".to_string(),
+            Value::Object(handle) => match self.get(*handle) {
+                HeapObject::Closure(closure) => format!("<fn {}>", closure.function.name),
+                HeapObject::BoundMethod(bound) => {
+                    format!("<fn {}>", self.closure(bound.method).function.name)
+                }
+                HeapObject::Class(class) => class.name.to_string(),
+                HeapObject::Instance(instance) => {
+                    format!("{} instance", self.class(instance.class).name)
+                }
+                HeapObject::Upvalue(_) => "upvalue".to_string(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance(heap: &mut Heap, class: ObjRef) -> ObjRef {
+        heap.alloc(HeapObject::Instance(Instance {
+            class,
+            fields: HashMap::new(),
+        }))
+    }
+
+    /// Allocates a class and two instances whose `other` fields point at
+    /// each other, returning the instances.
+    fn cycle(heap: &mut Heap) -> (ObjRef, ObjRef) {
+        let class = heap.alloc(HeapObject::Class(Class {
+            name: Symbol::intern("Node"),
+            methods: HashMap::new(),
+        }));
+        let a = instance(heap, class);
+        let b = instance(heap, class);
+        let other = Symbol::intern("other");
+        for (from, to) in [(a, b), (b, a)] {
+            let HeapObject::Instance(instance) = heap.get_mut(from) else {
+                unreachable!();
+            };
+            instance.fields.insert(other, Value::Object(to));
+        }
+        (a, b)
+    }
+
+    #[test]
+    fn collect_keeps_reachable_cycles() {
+        let mut heap = Heap::new(DEFAULT_GROWTH_FACTOR, false);
+        let (a, b) = cycle(&mut heap);
+        heap.collect([a]);
+        assert_eq!(heap.live, 3);
+        assert!(matches!(heap.get(b), HeapObject::Instance(_)));
+    }
+
+    #[test]
+    fn collect_frees_unreachable_cycles() {
+        let mut heap = Heap::new(DEFAULT_GROWTH_FACTOR, false);
+        cycle(&mut heap);
+        heap.collect([]);
+        assert_eq!(heap.live, 0);
+        assert!(heap.objects.iter().all(Option::is_none));
+        assert_eq!(heap.free.len(), 3);
+    }
+}
@@ -0,0 +1,658 @@
+use std::{
+    collections::HashMap,
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    chunk::{Constant, Function, OpCode},
+    debug,
+    interpreter::InterpreterError,
+    symbol::Symbol,
+    token::{Token, TokenType},
+    value::{
+        BoundMethod, Class, Closure, Heap, HeapObject, Instance, Native, ObjRef, Upvalue, Value,
+    },
+};
+
+/// How deep calls can nest before the VM reports a stack overflow.
+pub const FRAMES_MAX: usize = 1024;
+
+/// A function call in progress.
+struct Frame {
+    closure: ObjRef,
+    function: Rc<Function>,
+    ip: usize,
+    /// Index of the stack slot holding the callee; its locals follow it.
+    slots: usize,
+}
+
+impl Frame {
+    fn read_byte(&mut self) -> u8 {
+        let byte = self.function.chunk.code[self.ip];
+        self.ip += 1;
+        byte
+    }
+
+    fn read_u16(&mut self) -> u16 {
+        let value = self.function.chunk.read_u16(self.ip);
+        self.ip += 2;
+        value
+    }
+
+    fn read_constant(&mut self) -> &Constant {
+        let index = self.read_u16() as usize;
+        &self.function.chunk.constants[index]
+    }
+
+    fn read_name(&mut self) -> Symbol {
+        match self.read_constant() {
+            Constant::Name(name) => *name,
+            constant => unreachable!("expected a name, found {constant:?}"),
+        }
+    }
+
+    /// A token for error reporting, placed at the instruction starting at
+    /// `offset`.
+    fn token(&self, offset: usize, ttype: TokenType, lexeme: &str) -> Token {
+        Token::new(ttype, lexeme, self.function.chunk.span(offset))
+    }
+
+    fn name_token(&self, offset: usize, name: &str) -> Token {
+        self.token(offset, TokenType::Identifier(Symbol::intern(name)), name)
+    }
+}
+
+/// A stack-based virtual machine that runs bytecode from the [`Compiler`].
+/// It prints the same output and reports the same runtime errors as the
+/// tree-walking [`Interpreter`], but the compiler rejects programs that
+/// don't fit the bytecode: more than 255 locals or 256 upvalues in a
+/// function, more than 65,536 constants in one chunk, or jumps over more
+/// than 64 KiB of code.
+///
+/// [`Compiler`]: crate::compiler::Compiler
+/// [`Interpreter`]: crate::interpreter::Interpreter
+pub struct Vm {
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    globals: HashMap<Symbol, Value>,
+    heap: Heap,
+    /// Upvalues still pointing into the stack, sorted by slot.
+    open_upvalues: Vec<ObjRef>,
+    /// Print the stack and each instruction to stderr as it runs.
+    trace_execution: bool,
+}
+
+impl Vm {
+    pub fn new(heap: Heap, trace_execution: bool) -> Self {
+        let mut globals = HashMap::new();
+        globals.insert(
+            Symbol::intern("clock"),
+            Value::Native(Rc::new(Native {
+                arity: 0,
+                function: |_| {
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .expect("System clock is set before the Unix epoch");
+                    Value::Number(now.as_secs_f64())
+                },
+            })),
+        );
+
+        Self {
+            stack: Vec::new(),
+            frames: Vec::new(),
+            globals,
+            heap,
+            open_upvalues: Vec::new(),
+            trace_execution,
+        }
+    }
+
+    pub fn interpret(&mut self, script: Rc<Function>) -> Result<(), InterpreterError> {
+        let closure = self.heap.alloc(HeapObject::Closure(Closure {
+            function: script.clone(),
+            upvalues: Vec::new(),
+        }));
+        self.stack.push(Value::Object(closure));
+        let frame = Frame {
+            closure,
+            function: script,
+            ip: 0,
+            slots: 0,
+        };
+
+        let result = self.run(frame);
+        if result.is_err() {
+            self.stack.clear();
+            self.frames.clear();
+            self.open_upvalues.clear();
+        }
+        result
+    }
+
+    fn run(&mut self, mut frame: Frame) -> Result<(), InterpreterError> {
+        loop {
+            let start = frame.ip;
+            if self.heap.should_collect() {
+                self.collect_garbage(&frame);
+            }
+            if self.trace_execution {
+                self.trace(&frame);
+            }
+            let op = OpCode::from_byte(frame.read_byte()).expect("invalid opcode");
+
+            match op {
+                OpCode::Constant => {
+                    let value = match frame.read_constant() {
+                        Constant::Number(number) => Value::Number(*number),
+                        Constant::String(string) => Value::String(string.clone()),
+                        Constant::Name(_) | Constant::Function(_) => {
+                            unreachable!("names and functions aren't values")
+                        }
+                    };
+                    self.stack.push(value);
+                }
+                OpCode::Nil => self.stack.push(Value::Nil),
+                OpCode::True => self.stack.push(Value::Bool(true)),
+                OpCode::False => self.stack.push(Value::Bool(false)),
+                OpCode::Pop => {
+                    self.pop();
+                }
+                OpCode::GetLocal => {
+                    let slot = frame.slots + frame.read_byte() as usize;
+                    self.stack.push(self.stack[slot].clone());
+                }
+                OpCode::SetLocal => {
+                    let slot = frame.slots + frame.read_byte() as usize;
+                    self.stack[slot] = self.peek(0).clone();
+                }
+                OpCode::GetGlobal => {
+                    let name = frame.read_name();
+                    let Some(value) = self.globals.get(&name) else {
+                        return Err(InterpreterError::UndefinedVariable {
+                            name: frame.name_token(start, &name),
+                        });
+                    };
+                    self.stack.push(value.clone());
+                }
+                OpCode::DefineGlobal => {
+                    let name = frame.read_name();
+                    let value = self.pop();
+                    self.globals.insert(name, value);
+                }
+                OpCode::SetGlobal => {
+                    let name = frame.read_name();
+                    let value = self.peek(0).clone();
+                    let Some(global) = self.globals.get_mut(&name) else {
+                        return Err(InterpreterError::UndefinedVariable {
+                            name: frame.name_token(start, &name),
+                        });
+                    };
+                    *global = value;
+                }
+                OpCode::GetUpvalue => {
+                    let index = frame.read_byte() as usize;
+                    let upvalue = self.heap.closure(frame.closure).upvalues[index];
+                    let value = match self.heap.upvalue_mut(upvalue) {
+                        Upvalue::Open(slot) => self.stack[*slot].clone(),
+                        Upvalue::Closed(value) => value.clone(),
+                    };
+                    self.stack.push(value);
+                }
+                OpCode::SetUpvalue => {
+                    let index = frame.read_byte() as usize;
+                    let upvalue = self.heap.closure(frame.closure).upvalues[index];
+                    let value = self.peek(0).clone();
+                    match self.heap.upvalue_mut(upvalue) {
+                        Upvalue::Open(slot) => self.stack[*slot] = value,
+                        Upvalue::Closed(closed) => *closed = value,
+                    }
+                }
+                OpCode::GetProperty => {
+                    let name = frame.read_name();
+                    let instance = match self.peek(0) {
+                        Value::Object(handle) => match self.heap.get(*handle) {
+                            HeapObject::Instance(instance) => instance,
+                            _ => return Err(only_instances_have_properties(&frame, start, &name)),
+                        },
+                        _ => return Err(only_instances_have_properties(&frame, start, &name)),
+                    };
+
+                    if let Some(value) = instance.fields.get(&name) {
+                        let value = value.clone();
+                        self.pop();
+                        self.stack.push(value);
+                    } else {
+                        let class = instance.class;
+                        let receiver = self.pop();
+                        let bound = self.bind_method(class, name, receiver, &frame, start)?;
+                        self.stack.push(bound);
+                    }
+                }
+                OpCode::SetProperty => {
+                    let name = frame.read_name();
+                    let value = self.pop();
+                    let object = self.pop();
+                    let instance = match object {
+                        Value::Object(handle) => match self.heap.get_mut(handle) {
+                            HeapObject::Instance(instance) => instance,
+                            _ => return Err(only_instances_have_fields(&frame, start, &name)),
+                        },
+                        _ => return Err(only_instances_have_fields(&frame, start, &name)),
+                    };
+                    instance.fields.insert(name, value.clone());
+                    self.stack.push(value);
+                }
+                OpCode::GetSuper => {
+                    let name = frame.read_name();
+                    let superclass = self.pop();
+                    let superclass = self.class(&superclass, &frame, start)?;
+                    let receiver = self.pop();
+                    let bound = self.bind_method(superclass, name, receiver, &frame, start)?;
+                    self.stack.push(bound);
+                }
+                OpCode::Equal => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    self.stack.push(Value::Bool(left == right));
+                }
+                OpCode::NotEqual => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    self.stack.push(Value::Bool(left != right));
+                }
+                OpCode::Greater => {
+                    let (l, r) = self.number_operands(&frame, start, TokenType::Greater, ">")?;
+                    self.stack.push(Value::Bool(l > r));
+                }
+                OpCode::GreaterEqual => {
+                    let (l, r) =
+                        self.number_operands(&frame, start, TokenType::GreaterEqual, ">=")?;
+                    self.stack.push(Value::Bool(l >= r));
+                }
+                OpCode::Less => {
+                    let (l, r) = self.number_operands(&frame, start, TokenType::Less, "<")?;
+                    self.stack.push(Value::Bool(l < r));
+                }
+                OpCode::LessEqual => {
+                    let (l, r) = self.number_operands(&frame, start, TokenType::LessEqual, "<=")?;
+                    self.stack.push(Value::Bool(l <= r));
+                }
+                OpCode::Add => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    let value = match (left, right) {
+                        (Value::Number(l), Value::Number(r)) => Value::Number(l + r),
+                        (Value::String(l), Value::String(r)) => {
+                            Value::String(format!("{l}{r}").into())
+                        }
+                        _ => {
+                            return Err(InterpreterError::OperandsMustBeNumbersOrStrings {
+                                operator: frame.token(start, TokenType::Plus, "+"),
+                            })
+                        }
+                    };
+                    self.stack.push(value);
+                }
+                OpCode::Subtract => {
+                    let (l, r) = self.number_operands(&frame, start, TokenType::Minus, "-")?;
+                    self.stack.push(Value::Number(l - r));
+                }
+                OpCode::Multiply => {
+                    let (l, r) = self.number_operands(&frame, start, TokenType::Star, "*")?;
+                    self.stack.push(Value::Number(l * r));
+                }
+                OpCode::Divide => {
+                    let (l, r) = self.number_operands(&frame, start, TokenType::Slash, "/")?;
+                    if r == 0.0 {
+                        return Err(InterpreterError::DivisionByZero {
+                            operator: frame.token(start, TokenType::Slash, "/"),
+                        });
+                    }
+                    self.stack.push(Value::Number(l / r));
+                }
+                OpCode::Not => {
+                    let value = self.pop();
+                    self.stack.push(Value::Bool(!value.is_truthy()));
+                }
+                OpCode::Negate => {
+                    let Value::Number(number) = self.pop() else {
+                        return Err(InterpreterError::OperandMustBeNumber {
+                            operator: frame.token(start, TokenType::Minus, "-"),
+                        });
+                    };
+                    self.stack.push(Value::Number(-number));
+                }
+                OpCode::Print => {
+                    let value = self.pop();
+                    println!("{}", self.heap.display(&value));
+                }
+                OpCode::Jump => {
+                    let offset = frame.read_u16() as usize;
+                    frame.ip += offset;
+                }
+                OpCode::JumpIfFalse => {
+                    let offset = frame.read_u16() as usize;
+                    if !self.peek(0).is_truthy() {
+                        frame.ip += offset;
+                    }
+                }
+                OpCode::Loop => {
+                    let offset = frame.read_u16() as usize;
+                    frame.ip -= offset;
+                }
+                OpCode::Call => {
+                    let argument_count = frame.read_byte() as usize;
+                    let paren = frame.token(start, TokenType::RightParen, ")");
+                    if let Some(callee) = self.call_value(argument_count, paren)? {
+                        self.frames.push(std::mem::replace(&mut frame, callee));
+                    }
+                }
+                OpCode::Closure => {
+                    let Constant::Function(function) = frame.read_constant().clone() else {
+                        unreachable!("Closure always loads a function");
+                    };
+                    let mut upvalues = Vec::with_capacity(function.upvalue_count);
+                    for _ in 0..function.upvalue_count {
+                        let is_local = frame.read_byte() == 1;
+                        let index = frame.read_byte() as usize;
+                        let upvalue = if is_local {
+                            self.capture_upvalue(frame.slots + index)
+                        } else {
+                            self.heap.closure(frame.closure).upvalues[index]
+                        };
+                        upvalues.push(upvalue);
+                    }
+                    let closure = self
+                        .heap
+                        .alloc(HeapObject::Closure(Closure { function, upvalues }));
+                    self.stack.push(Value::Object(closure));
+                }
+                OpCode::CloseUpvalue => {
+                    self.close_upvalues(self.stack.len() - 1);
+                    self.pop();
+                }
+                OpCode::Return => {
+                    let result = self.pop();
+                    self.close_upvalues(frame.slots);
+                    self.stack.truncate(frame.slots);
+                    match self.frames.pop() {
+                        Some(caller) => {
+                            self.stack.push(result);
+                            frame = caller;
+                        }
+                        None => return Ok(()),
+                    }
+                }
+                OpCode::Class => {
+                    let name = frame.read_name();
+                    let class = self.heap.alloc(HeapObject::Class(Class {
+                        name,
+                        methods: HashMap::new(),
+                    }));
+                    self.stack.push(Value::Object(class));
+                }
+                OpCode::Inherit => {
+                    let superclass = match self.peek(1) {
+                        Value::Object(handle)
+                            if matches!(self.heap.get(*handle), HeapObject::Class(_)) =>
+                        {
+                            *handle
+                        }
+                        _ => {
+                            return Err(InterpreterError::SuperclassMustBeClass {
+                                name: frame.name_token(start, ""),
+                            })
+                        }
+                    };
+                    let subclass = self.pop();
+                    let subclass = self.class(&subclass, &frame, start)?;
+                    let methods = self.heap.class(superclass).methods.clone();
+                    self.heap.class_mut(subclass).methods.extend(methods);
+                }
+                OpCode::Method => {
+                    let name = frame.read_name();
+                    let method = self.pop();
+                    let method = self.closure(&method, &frame, start)?;
+                    let class = self.class(self.peek(0), &frame, start)?;
+                    self.heap.class_mut(class).methods.insert(name, method);
+                }
+            }
+        }
+    }
+
+    /// Runs between instructions, when every live object is reachable from
+    /// the stack, a global, a call frame or an open upvalue.
+    fn collect_garbage(&mut self, frame: &Frame) {
+        let roots = self
+            .stack
+            .iter()
+            .chain(self.globals.values())
+            .filter_map(Value::object)
+            .chain(self.frames.iter().map(|frame| frame.closure))
+            .chain([frame.closure])
+            .chain(self.open_upvalues.iter().copied());
+        self.heap.collect(roots);
+    }
+
+    fn trace(&self, frame: &Frame) {
+        let mut line = String::from("          ");
+        for value in &self.stack {
+            line += &format!("[ {} ]", self.heap.display(value));
+        }
+        line += "\n";
+        debug::disassemble_instruction(&frame.function.chunk, frame.ip, &mut line);
+        eprint!("{line}");
+    }
+
+    /// The class `value` refers to. The compiler only hands classes to the
+    /// instructions that call this, but a `.loxc` file might not.
+    fn class(
+        &self,
+        value: &Value,
+        frame: &Frame,
+        start: usize,
+    ) -> Result<ObjRef, InterpreterError> {
+        match value {
+            Value::Object(handle) if matches!(self.heap.get(*handle), HeapObject::Class(_)) => {
+                Ok(*handle)
+            }
+            _ => Err(malformed_bytecode(frame, start, "a class")),
+        }
+    }
+
+    /// The closure `value` refers to, checked like [`Vm::class`].
+    fn closure(
+        &self,
+        value: &Value,
+        frame: &Frame,
+        start: usize,
+    ) -> Result<ObjRef, InterpreterError> {
+        match value {
+            Value::Object(handle) if matches!(self.heap.get(*handle), HeapObject::Closure(_)) => {
+                Ok(*handle)
+            }
+            _ => Err(malformed_bytecode(frame, start, "a closure")),
+        }
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("stack underflow")
+    }
+
+    fn peek(&self, distance: usize) -> &Value {
+        &self.stack[self.stack.len() - 1 - distance]
+    }
+
+    fn number_operands(
+        &mut self,
+        frame: &Frame,
+        start: usize,
+        ttype: TokenType,
+        lexeme: &str,
+    ) -> Result<(f64, f64), InterpreterError> {
+        let right = self.pop();
+        let left = self.pop();
+        match (left, right) {
+            (Value::Number(l), Value::Number(r)) => Ok((l, r)),
+            _ => Err(InterpreterError::OperandsMustBeNumbers {
+                operator: frame.token(start, ttype, lexeme),
+            }),
+        }
+    }
+
+    /// Calls the value sitting below `argument_count` arguments on the stack.
+    /// Returns the frame to switch to when the callee is Lox code; natives
+    /// and classes without an initializer finish right away.
+    fn call_value(
+        &mut self,
+        argument_count: usize,
+        paren: Token,
+    ) -> Result<Option<Frame>, InterpreterError> {
+        let callee_slot = self.stack.len() - 1 - argument_count;
+        let handle = match &self.stack[callee_slot] {
+            Value::Native(native) => {
+                let native = native.clone();
+                check_arity(native.arity, argument_count, &paren)?;
+                let result = (native.function)(&self.stack[callee_slot + 1..]);
+                self.stack.truncate(callee_slot);
+                self.stack.push(result);
+                return Ok(None);
+            }
+            Value::Object(handle) => *handle,
+            _ => return Err(InterpreterError::NotCallable { paren }),
+        };
+
+        match self.heap.get(handle) {
+            HeapObject::Closure(_) => self.call_closure(handle, argument_count, paren).map(Some),
+            HeapObject::BoundMethod(bound) => {
+                let method = bound.method;
+                self.stack[callee_slot] = bound.receiver.clone();
+                self.call_closure(method, argument_count, paren).map(Some)
+            }
+            HeapObject::Class(class) => {
+                let initializer = class.methods.get(&Symbol::intern("init")).copied();
+                let instance = self.heap.alloc(HeapObject::Instance(Instance {
+                    class: handle,
+                    fields: HashMap::new(),
+                }));
+                self.stack[callee_slot] = Value::Object(instance);
+                match initializer {
+                    Some(initializer) => self
+                        .call_closure(initializer, argument_count, paren)
+                        .map(Some),
+                    None => {
+                        check_arity(0, argument_count, &paren)?;
+                        Ok(None)
+                    }
+                }
+            }
+            HeapObject::Instance(_) | HeapObject::Upvalue(_) => {
+                Err(InterpreterError::NotCallable { paren })
+            }
+        }
+    }
+
+    fn call_closure(
+        &mut self,
+        closure: ObjRef,
+        argument_count: usize,
+        paren: Token,
+    ) -> Result<Frame, InterpreterError> {
+        let function = self.heap.closure(closure).function.clone();
+        check_arity(function.arity, argument_count, &paren)?;
+        if self.frames.len() + 1 >= FRAMES_MAX {
+            return Err(InterpreterError::StackOverflow { paren });
+        }
+
+        Ok(Frame {
+            closure,
+            function,
+            ip: 0,
+            slots: self.stack.len() - 1 - argument_count,
+        })
+    }
+
+    fn bind_method(
+        &mut self,
+        class: ObjRef,
+        name: Symbol,
+        receiver: Value,
+        frame: &Frame,
+        start: usize,
+    ) -> Result<Value, InterpreterError> {
+        let Some(&method) = self.heap.class(class).methods.get(&name) else {
+            return Err(InterpreterError::UndefinedProperty {
+                name: frame.name_token(start, &name),
+            });
+        };
+        let bound = self
+            .heap
+            .alloc(HeapObject::BoundMethod(BoundMethod { receiver, method }));
+        Ok(Value::Object(bound))
+    }
+
+    fn capture_upvalue(&mut self, slot: usize) -> ObjRef {
+        let mut insert_at = self.open_upvalues.len();
+        for (index, &upvalue) in self.open_upvalues.iter().enumerate() {
+            match self.heap.upvalue_mut(upvalue) {
+                Upvalue::Open(open) if *open == slot => return upvalue,
+                Upvalue::Open(open) if *open > slot => {
+                    insert_at = index;
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        let upvalue = self.heap.alloc(HeapObject::Upvalue(Upvalue::Open(slot)));
+        self.open_upvalues.insert(insert_at, upvalue);
+        upvalue
+    }
+
+    /// Moves every captured variable at or above `from` off the stack.
+    fn close_upvalues(&mut self, from: usize) {
+        while let Some(&upvalue) = self.open_upvalues.last() {
+            let upvalue = self.heap.upvalue_mut(upvalue);
+            let Upvalue::Open(slot) = *upvalue else {
+                unreachable!("closed upvalues are removed from the open list");
+            };
+            if slot < from {
+                break;
+            }
+            *upvalue = Upvalue::Closed(self.stack[slot].clone());
+            self.open_upvalues.pop();
+        }
+    }
+}
+
+fn check_arity(expected: usize, got: usize, paren: &Token) -> Result<(), InterpreterError> {
+    if expected == got {
+        return Ok(());
+    }
+    Err(InterpreterError::ArityMismatch {
+        paren: paren.clone(),
+        expected,
+        got,
+    })
+}
+
+fn only_instances_have_properties(frame: &Frame, start: usize, name: &str) -> InterpreterError {
+    InterpreterError::OnlyInstancesHaveProperties {
+        name: frame.name_token(start, name),
+    }
+}
+
+fn only_instances_have_fields(frame: &Frame, start: usize, name: &str) -> InterpreterError {
+    InterpreterError::OnlyInstancesHaveFields {
+        name: frame.name_token(start, name),
+    }
+}
+
+fn malformed_bytecode(frame: &Frame, start: usize, expected: &'static str) -> InterpreterError {
+    InterpreterError::MalformedBytecode {
+        at: frame.name_token(start, ""),
+        expected,
+    }
+}
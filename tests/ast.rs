@@ -0,0 +1,13 @@
+mod common;
+
+use common::lox;
+
+#[test]
+fn ast_format_dot_prints_a_graph() {
+    let output = lox(["ast", "--ast-format=dot", "-e", "print 1 + 2;"]);
+    assert_eq!(output.stderr, "");
+    assert_eq!(output.status, Some(0));
+    assert!(output.stdout.starts_with("digraph ast {\n"), "{}", output.stdout);
+    assert!(output.stdout.ends_with("}\n"), "{}", output.stdout);
+    assert!(output.stdout.contains("[label=\"+\", shape=box]"));
+}
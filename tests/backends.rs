@@ -0,0 +1,25 @@
+mod common;
+
+use std::ffi::OsStr;
+
+use common::{lox, programs};
+
+#[test]
+fn tree_walker_and_vm_agree() {
+    for program in programs() {
+        let tree = lox([OsStr::new("--backend=tree"), program.as_os_str()]);
+        let vm = lox([OsStr::new("--backend=vm"), program.as_os_str()]);
+        assert_eq!(tree, vm, "backends disagree on {}", program.display());
+    }
+}
+
+#[test]
+fn corpus_covers_success_and_errors() {
+    let statuses: Vec<_> = programs()
+        .iter()
+        .map(|program| lox([program]).status)
+        .collect();
+    for status in [Some(0), Some(65), Some(70)] {
+        assert!(statuses.contains(&status), "no program exits with {status:?}");
+    }
+}
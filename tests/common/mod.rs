@@ -0,0 +1,46 @@
+// Every test crate compiles this module but uses only some of it.
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// What one run of the `lox` binary printed and how it exited.
+#[derive(Debug, PartialEq)]
+pub struct Output {
+    pub stdout: String,
+    pub stderr: String,
+    pub status: Option<i32>,
+}
+
+/// Runs the `lox` binary with `args`, never coloring its errors.
+pub fn lox<I, S>(args: I) -> Output
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<std::ffi::OsStr>,
+{
+    let output = Command::new(env!("CARGO_BIN_EXE_lox"))
+        .arg("--no-color")
+        .args(args)
+        .output()
+        .expect("failed to run lox");
+    Output {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        status: output.status.code(),
+    }
+}
+
+/// Every `.lox` file in `tests/programs`, in a stable order.
+pub fn programs() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/programs");
+    let mut programs: Vec<_> = fs::read_dir(dir)
+        .expect("failed to read tests/programs")
+        .map(|entry| entry.expect("failed to read tests/programs").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lox"))
+        .collect();
+    programs.sort();
+    programs
+}
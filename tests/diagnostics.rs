@@ -0,0 +1,40 @@
+mod common;
+
+use common::lox;
+
+#[test]
+fn json_diagnostics_carry_code_and_span() {
+    let output = lox([
+        "check",
+        "--error-format=json",
+        "-e",
+        "var a;\n(a +\n  a) = 1;\nif (a { print a; }",
+    ]);
+    assert_eq!(output.stdout, "");
+    assert_eq!(output.status, Some(65));
+    let lines: Vec<_> = output.stderr.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            r#"{"severity":"error","code":"E0101","message":"Invalid assignment target.","file":"<inline>","line":2,"column":1,"end_line":3,"end_column":5,"label":"can't assign to this","notes":[]}"#,
+            r#"{"severity":"error","code":"E0104","message":"Expect ')' after if condition.","file":"<inline>","line":4,"column":7,"end_line":4,"end_column":8,"label":"at '{'","notes":[]}"#,
+            r#"{"severity":"error","code":"E0100","message":"Expect expression.","file":"<inline>","line":4,"column":18,"end_line":4,"end_column":19,"label":"at '}'","notes":[]}"#,
+        ]
+    );
+}
+
+#[test]
+fn json_diagnostics_name_the_script_file() {
+    let program = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/programs/error_division_by_zero.lox"
+    );
+    let output = lox(["--error-format=json", program]);
+    assert_eq!(output.status, Some(70));
+    assert_eq!(
+        output.stderr,
+        format!(
+            r#"{{"severity":"error","code":"E0303","message":"Division by zero.","file":"{program}","line":1,"column":8,"end_line":1,"end_column":9,"label":"","notes":[]}}"#
+        ) + "\n"
+    );
+}
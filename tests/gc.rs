@@ -0,0 +1,79 @@
+mod common;
+
+use std::ffi::OsStr;
+
+use common::{lox, programs};
+
+#[test]
+fn collecting_often_changes_no_output() {
+    for program in programs() {
+        let normal = lox([OsStr::new("--backend=vm"), program.as_os_str()]);
+        for flag in ["--gc-stress", "--gc-growth=1"] {
+            let collected = lox([
+                OsStr::new("--backend=vm"),
+                OsStr::new(flag),
+                program.as_os_str(),
+            ]);
+            assert_eq!(collected, normal, "{flag} {}", program.display());
+        }
+    }
+}
+
+/// Runs `source` on the VM, collecting after every allocation.
+fn stress(source: &str) -> String {
+    let output = lox(["--backend=vm", "--gc-stress", "-e", source]);
+    assert_eq!(output.stderr, "");
+    assert_eq!(output.status, Some(0));
+    output.stdout
+}
+
+#[test]
+fn open_upvalues_survive_collection() {
+    // Once `peek` is gone, only the VM's list of open upvalues refers to the
+    // upvalue for `x`; `later` has to find that same upvalue again.
+    let source = r#"
+        class Box { init(value) { this.value = value; } }
+        fun outer() {
+          var x = Box("kept");
+          { fun peek() { return x; } }
+          var garbage = Box("garbage");
+          fun later() { x.value = x.value + "!"; return x.value; }
+          later();
+          return later;
+        }
+        var later = outer();
+        Box("more garbage");
+        print later();
+    "#;
+    assert_eq!(stress(source), "kept!!\n");
+}
+
+#[test]
+fn closed_upvalues_survive_collection() {
+    let source = r#"
+        class Box { init(value) { this.value = value; } }
+        fun make() { var box = Box("closed"); fun get() { return box.value; } return get; }
+        var get = make();
+        for (var i = 0; i < 10; i = i + 1) Box(i);
+        print get();
+    "#;
+    assert_eq!(stress(source), "closed\n");
+}
+
+#[test]
+fn bound_methods_keep_their_receiver_and_method() {
+    // The instance and its class are only reachable through `describe`.
+    let source = r#"
+        var describe;
+        {
+          class Named {
+            init(name) { this.name = name; }
+            describe() { return "I am " + this.name; }
+          }
+          describe = Named("bound").describe;
+        }
+        for (var i = 0; i < 10; i = i + 1) { fun junk() {} }
+        print describe();
+    "#;
+    assert_eq!(stress(source), "I am bound\n");
+}
@@ -0,0 +1,176 @@
+mod common;
+
+use std::{ffi::OsStr, path::Path};
+
+use common::{lox, programs};
+
+#[test]
+fn compiled_programs_run_like_their_source() {
+    let out_dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    for program in programs() {
+        let loxc = out_dir.join(program.with_extension("loxc").file_name().unwrap());
+        let compiled = lox([
+            OsStr::new("compile"),
+            program.as_os_str(),
+            OsStr::new("-o"),
+            loxc.as_os_str(),
+        ]);
+        if compiled.status != Some(0) {
+            // Programs with compile errors have no bytecode to run.
+            assert_eq!(compiled.status, Some(65), "{}", program.display());
+            continue;
+        }
+
+        let source = lox([OsStr::new("--backend=vm"), program.as_os_str()]);
+        let bytecode = lox([loxc.as_os_str()]);
+        assert_eq!(bytecode.stdout, source.stdout, "{}", program.display());
+        assert_eq!(bytecode.status, source.status, "{}", program.display());
+    }
+}
+
+#[test]
+fn corrupt_files_are_rejected() {
+    let out_dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let program = &programs()[0];
+    let loxc = out_dir.join("corrupt.loxc");
+    lox([
+        OsStr::new("compile"),
+        program.as_os_str(),
+        OsStr::new("-o"),
+        loxc.as_os_str(),
+    ]);
+    let mut bytes = std::fs::read(&loxc).unwrap();
+    *bytes.last_mut().unwrap() ^= 1;
+    std::fs::write(&loxc, bytes).unwrap();
+
+    let output = lox([loxc.as_os_str()]);
+    assert_eq!(output.stdout, "");
+    assert!(output.stderr.contains("E0602"), "{}", output.stderr);
+    assert_eq!(output.status, Some(65));
+}
+
+// Opcodes and constant tags of format version 2, for building files by hand.
+const VERSION: u16 = 2;
+const NIL: u8 = 1;
+const POP: u8 = 4;
+const GET_SUPER: u8 = 14;
+const CLOSURE: u8 = 32;
+const RETURN: u8 = 34;
+const CLASS: u8 = 35;
+const INHERIT: u8 = 36;
+const METHOD: u8 = 37;
+const FUNCTION: u8 = 2;
+const NAME: u8 = 3;
+
+enum Constant {
+    Name(&'static str),
+    Function(&'static [u8]),
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: usize) {
+    bytes.extend_from_slice(&(value as u32).to_be_bytes());
+}
+
+fn write_string(bytes: &mut Vec<u8>, string: &str) {
+    write_u32(bytes, string.len());
+    bytes.extend_from_slice(string.as_bytes());
+}
+
+fn write_function(bytes: &mut Vec<u8>, name: &str, code: &[u8], constants: &[Constant]) {
+    write_string(bytes, name);
+    write_u32(bytes, 0);
+    write_u32(bytes, 0);
+    write_u32(bytes, code.len());
+    bytes.extend_from_slice(code);
+    write_u32(bytes, constants.len());
+    for constant in constants {
+        match constant {
+            Constant::Name(name) => {
+                bytes.push(NAME);
+                write_string(bytes, name);
+            }
+            Constant::Function(code) => {
+                bytes.push(FUNCTION);
+                write_function(bytes, "method", code, &[]);
+            }
+        }
+    }
+    write_u32(bytes, 1);
+    for field in [0, 0, 1, 1, 1] {
+        write_u32(bytes, field);
+    }
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Writes a `.loxc` file with a valid header and checksum around the given
+/// script, and runs it.
+fn run_bytecode(file: &str, code: &[u8], constants: &[Constant]) -> common::Output {
+    let mut payload = Vec::new();
+    write_string(&mut payload, "crafted.lox");
+    write_function(&mut payload, "script", code, constants);
+
+    let mut bytes = b"LOXC".to_vec();
+    bytes.extend_from_slice(&VERSION.to_be_bytes());
+    bytes.extend_from_slice(&crc32(&payload).to_be_bytes());
+    bytes.extend_from_slice(&payload);
+
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join(file);
+    std::fs::write(&path, bytes).unwrap();
+    lox([path.as_os_str()])
+}
+
+fn assert_malformed_at_runtime(output: common::Output, expected: &str) {
+    assert_eq!(output.stdout, "");
+    assert!(
+        output.stderr.contains("E0312") && output.stderr.contains(expected),
+        "{}",
+        output.stderr
+    );
+    assert_eq!(output.status, Some(70));
+}
+
+#[test]
+fn well_formed_crafted_files_run() {
+    let output = run_bytecode("well_formed.loxc", &[NIL, RETURN], &[]);
+    assert_eq!(output.stderr, "");
+    assert_eq!(output.status, Some(0));
+}
+
+#[test]
+fn super_must_be_a_class() {
+    let code = [NIL, NIL, GET_SUPER, 0, 0, POP, NIL, RETURN];
+    let output = run_bytecode("get_super.loxc", &code, &[Constant::Name("m")]);
+    assert_malformed_at_runtime(output, "expected a class");
+}
+
+#[test]
+fn subclass_must_be_a_class() {
+    let code = [CLASS, 0, 0, NIL, INHERIT, POP, NIL, RETURN];
+    let output = run_bytecode("inherit.loxc", &code, &[Constant::Name("C")]);
+    assert_malformed_at_runtime(output, "expected a class");
+}
+
+#[test]
+fn method_must_be_a_closure() {
+    let code = [NIL, NIL, METHOD, 0, 0, POP, NIL, RETURN];
+    let output = run_bytecode("method.loxc", &code, &[Constant::Name("m")]);
+    assert_malformed_at_runtime(output, "expected a closure");
+}
+
+#[test]
+fn methods_must_be_defined_on_a_class() {
+    let code = [NIL, CLOSURE, 0, 1, METHOD, 0, 0, POP, NIL, RETURN];
+    let constants = [Constant::Name("m"), Constant::Function(&[NIL, RETURN])];
+    let output = run_bytecode("method_on_nil.loxc", &code, &constants);
+    assert_malformed_at_runtime(output, "expected a class");
+}
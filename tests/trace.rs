@@ -0,0 +1,40 @@
+mod common;
+
+use common::lox;
+
+#[test]
+fn trace_execution_prints_the_stack_before_each_instruction() {
+    let output = lox(["--trace-execution", "-e", "var a = 1;\nprint a + 2;"]);
+    assert_eq!(output.stdout, "3\n");
+    assert_eq!(output.status, Some(0));
+    let expected = r#"          [ <fn script> ]
+0000    1 Constant            0 '1'
+          [ <fn script> ][ 1 ]
+0003    | DefineGlobal        1 "a"
+          [ <fn script> ]
+0006    2 GetGlobal           1 "a"
+          [ <fn script> ][ 1 ]
+0009    | Constant            2 '2'
+          [ <fn script> ][ 1 ][ 2 ]
+0012    | Add
+          [ <fn script> ][ 3 ]
+0013    | Print
+          [ <fn script> ]
+0014    | Nil
+          [ <fn script> ][ nil ]
+0015    | Return
+"#;
+    assert_eq!(output.stderr, expected);
+}
+
+#[test]
+fn trace_execution_shows_captured_variables() {
+    let source = "fun f() { var x = 1; fun g() { return x; } return g(); }\nprint f();";
+    let output = lox(["--trace-execution", "-e", source]);
+    assert_eq!(output.stdout, "1\n");
+    assert!(output.stderr.contains("|                     local 1\n"));
+    assert!(output.stderr.contains("GetUpvalue          0\n"));
+    assert!(output
+        .stderr
+        .contains("          [ <fn script> ][ <fn f> ][ 1 ][ <fn g> ]\n"));
+}